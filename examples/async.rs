@@ -0,0 +1,16 @@
+use tokio_stream::StreamExt;
+
+fn main() {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    runtime.block_on(async {
+        let mut outputs = mrh::Crawler::new(".")
+            .pending(true)
+            .async_concurrency(4)
+            .crawl_async();
+        while let Some(output) = outputs.next().await {
+            println!("{output:?}");
+        }
+    });
+}
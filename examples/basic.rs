@@ -3,5 +3,5 @@ fn main() {
         .pending(true)
         .ignore_untracked(true)
         .ignore_uncommitted_repos(true)
-        .for_each(|output| println!("{output:?}"));
+        .for_each(|output| println!("{output}"));
 }
@@ -0,0 +1,9 @@
+fn main() {
+    let outputs = mrh::Crawler::new(".")
+        .pending(true)
+        .parallel(4)
+        .collect_parallel();
+    for output in outputs {
+        println!("{output:?}");
+    }
+}
@@ -3,18 +3,51 @@
 //! A Git repo can be in a number of states where some pending actions may
 //! need to be taken:
 //!
-//! - uncommitted changes
+//! - conflicts
+//! - merge, rebase, cherry-pick, revert, bisect, or am in progress (optional)
+//! - staged changes
+//! - whitespace errors in staged changes (optional)
+//! - unstaged changes
+//! - uncommitted changes (optional, replaces staged and unstaged changes)
 //! - unpushed commits
+//! - unpushed commits on other branches (optional)
+//! - wip commits (optional)
+//! - unsigned commits (optional)
 //! - outdated branch
+//! - diverged (optional, replaces unpushed commits and outdated branch)
 //! - added files
 //! - deleted files
 //! - renamed files
 //! - untracked files (can be disabled)
 //! - uncommitted repos (can be disabled)
+//! - empty repo (optional)
+//! - ignored files present (optional)
 //! - untagged HEAD (optional)
+//! - dangling tags (optional)
+//! - shallow clone (optional)
+//! - detached HEAD (optional)
+//! - not on default branch (optional)
 //! - unpushed tags (optional)
 //! - unpulled tags (optional)
+//! - upstream is local branch
+//! - HEAD on remote-tracking ref
 //! - unfetched commits (optional)
+//! - prune needed (optional)
+//! - can't fast-forward (optional)
+//! - default branch renamed (optional)
+//! - unpushed to all remotes (optional)
+//! - stashed changes (optional)
+//! - local excludes present (optional)
+//! - submodule pointer changed
+//! - modified submodule (optional)
+//! - uninitialized submodule (optional)
+//! - recent hard reset (optional)
+//! - stale (optional)
+//! - remote path missing (optional)
+//! - no remote (optional)
+//! - no upstream (optional)
+//! - upstream gone (optional)
+//! - possible secrets (optional)
 //!
 //! This library is meant to inspect those states, given a root path as
 //! starting point.
@@ -34,16 +67,281 @@
 //!
 //! ## Feature flags
 //!
-//! - __`cli`__: enabled by default, this allows building the CLI
+//! - __`cli`__: enabled by default, this allows building the CLI,
+//!   and brings in __`parallel`__.
 //! - __`json`__: useful only when __`cli`__ feature is provided,
-//! this provides output in JSON format, to ease consumption by tools.
+//!   this provides output in JSON format, to ease consumption by tools.
+//!   It also provides [`Crawler::remote_cache`], which is kept as JSON.
+//! - __`yaml`__ and __`toml`__: like __`json`__, for those formats.
+//! - __`dashboard`__: useful only when __`cli`__ feature is provided,
+//!   this provides a full-screen view that refreshes in place.
+//! - __`parallel`__: provides [`Crawler::collect_parallel`],
+//!   which inspects repos on a thread pool.
+//! - __`serde`__: implements `Serialize` for [`Output`],
+//!   in the shape the CLI uses for JSON output.
+//! - __`tokio`__: provides [`Crawler::crawl_async`],
+//!   which streams results, inspecting repos on tokio's blocking pool.
 
-use std::path::{Path, PathBuf};
+mod path_cache;
+#[cfg(feature = "json")]
+mod remote_cache;
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
 
 use dirs_next as dirs;
 use git2::{Branch, Delta, Error, Repository, StatusOptions};
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use indexmap::set::IndexSet as Set;
 
+/// A pending action that a repo may need
+///
+/// `Display` gives the human-readable form, like "unpushed commits".
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PendingState {
+    Conflicts,
+    MergeInProgress,
+    RebaseInProgress,
+    CherryPickInProgress,
+    RevertInProgress,
+    BisectInProgress,
+    ApplyMailboxInProgress,
+    StagedChanges,
+    WhitespaceErrors,
+    UnstagedChanges,
+    UncommittedChanges,
+    UnpushedCommits,
+    UnpushedOtherBranches,
+    WipCommits,
+    UnsignedCommits,
+    OutdatedBranch,
+    BranchDiverged,
+    AddedFiles,
+    DeletedFiles,
+    RenamedFiles,
+    UntrackedFiles,
+    EmptyRepo,
+    IgnoredFiles,
+    UntaggedHead,
+    DanglingTags,
+    ShallowClone,
+    DetachedHead,
+    NotOnDefaultBranch,
+    UnpushedTags,
+    UnpulledTags,
+    UpstreamIsLocalBranch,
+    HeadOnRemoteTrackingRef,
+    UnfetchedCommits,
+    PruneNeeded,
+    CantFastForward,
+    DefaultBranchRenamed,
+    UnpushedToAllRemotes,
+    StashedChanges,
+    LocalExcludes,
+    SubmodulePointerChanged,
+    ModifiedSubmodule,
+    UninitializedSubmodule,
+    RecentHardReset,
+    Stale,
+    RemotePathMissing,
+    NoRemote,
+    NoUpstream,
+    UpstreamGone,
+    PossibleSecrets,
+}
+
+impl PendingState {
+    /// Every state, in the order they are listed in the crate docs
+    pub const ALL: &'static [Self] = &[
+        Self::Conflicts,
+        Self::MergeInProgress,
+        Self::RebaseInProgress,
+        Self::CherryPickInProgress,
+        Self::RevertInProgress,
+        Self::BisectInProgress,
+        Self::ApplyMailboxInProgress,
+        Self::StagedChanges,
+        Self::WhitespaceErrors,
+        Self::UnstagedChanges,
+        Self::UncommittedChanges,
+        Self::UnpushedCommits,
+        Self::UnpushedOtherBranches,
+        Self::WipCommits,
+        Self::UnsignedCommits,
+        Self::OutdatedBranch,
+        Self::BranchDiverged,
+        Self::AddedFiles,
+        Self::DeletedFiles,
+        Self::RenamedFiles,
+        Self::UntrackedFiles,
+        Self::EmptyRepo,
+        Self::IgnoredFiles,
+        Self::UntaggedHead,
+        Self::DanglingTags,
+        Self::ShallowClone,
+        Self::DetachedHead,
+        Self::NotOnDefaultBranch,
+        Self::UnpushedTags,
+        Self::UnpulledTags,
+        Self::UpstreamIsLocalBranch,
+        Self::HeadOnRemoteTrackingRef,
+        Self::UnfetchedCommits,
+        Self::PruneNeeded,
+        Self::CantFastForward,
+        Self::DefaultBranchRenamed,
+        Self::UnpushedToAllRemotes,
+        Self::StashedChanges,
+        Self::LocalExcludes,
+        Self::SubmodulePointerChanged,
+        Self::ModifiedSubmodule,
+        Self::UninitializedSubmodule,
+        Self::RecentHardReset,
+        Self::Stale,
+        Self::RemotePathMissing,
+        Self::NoRemote,
+        Self::NoUpstream,
+        Self::UpstreamGone,
+        Self::PossibleSecrets,
+    ];
+
+    /// The human-readable form, as also used by `Display`
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Conflicts => "conflicts",
+            Self::MergeInProgress => "merge in progress",
+            Self::RebaseInProgress => "rebase in progress",
+            Self::CherryPickInProgress => "cherry-pick in progress",
+            Self::RevertInProgress => "revert in progress",
+            Self::BisectInProgress => "bisect in progress",
+            Self::ApplyMailboxInProgress => "am in progress",
+            Self::StagedChanges => "staged changes",
+            Self::WhitespaceErrors => "whitespace errors",
+            Self::UnstagedChanges => "unstaged changes",
+            Self::UncommittedChanges => "uncommitted changes",
+            Self::UnpushedCommits => "unpushed commits",
+            Self::UnpushedOtherBranches => "unpushed commits on other branches",
+            Self::WipCommits => "wip commits",
+            Self::UnsignedCommits => "unsigned commits",
+            Self::OutdatedBranch => "outdated branch",
+            Self::BranchDiverged => "diverged",
+            Self::AddedFiles => "added files",
+            Self::DeletedFiles => "deleted files",
+            Self::RenamedFiles => "renamed files",
+            Self::UntrackedFiles => "untracked files",
+            Self::EmptyRepo => "empty repo",
+            Self::IgnoredFiles => "ignored files present",
+            Self::UntaggedHead => "untagged HEAD",
+            Self::DanglingTags => "dangling tags",
+            Self::ShallowClone => "shallow clone",
+            Self::DetachedHead => "detached HEAD",
+            Self::NotOnDefaultBranch => "not on default branch",
+            Self::UnpushedTags => "unpushed tags",
+            Self::UnpulledTags => "unpulled tags",
+            Self::UpstreamIsLocalBranch => "upstream is local branch",
+            Self::HeadOnRemoteTrackingRef => "HEAD on remote-tracking ref",
+            Self::UnfetchedCommits => "unfetched commits",
+            Self::PruneNeeded => "prune needed",
+            Self::CantFastForward => "can't fast-forward",
+            Self::DefaultBranchRenamed => "default branch renamed",
+            Self::UnpushedToAllRemotes => "unpushed to all remotes",
+            Self::StashedChanges => "stashed changes",
+            Self::LocalExcludes => "local excludes present",
+            Self::SubmodulePointerChanged => "submodule pointer changed",
+            Self::ModifiedSubmodule => "modified submodule",
+            Self::UninitializedSubmodule => "uninitialized submodule",
+            Self::RecentHardReset => "recent hard reset",
+            Self::Stale => "stale",
+            Self::RemotePathMissing => "remote path missing",
+            Self::NoRemote => "no remote",
+            Self::NoUpstream => "no upstream",
+            Self::UpstreamGone => "upstream gone",
+            Self::PossibleSecrets => "possible secrets",
+        }
+    }
+}
+
+impl std::fmt::Display for PendingState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Parses the human-readable form, also with dashes in place of spaces,
+/// like "outdated-branch", which is handier on the command line
+impl std::str::FromStr for PendingState {
+    type Err = UnknownState;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let normalize = |state: &str| state.trim().replace('-', " ");
+        let wanted = normalize(text);
+        match Self::ALL
+            .iter()
+            .find(|state| normalize(state.as_str()) == wanted)
+        {
+            Some(state) => Ok(*state),
+            None => Err(UnknownState(wanted)),
+        }
+    }
+}
+
+/// A string that names none of the [`PendingState`]s
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownState(pub String);
+
+impl std::fmt::Display for UnknownState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "unknown pending state: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownState {}
+
+/// Why a repo could not be inspected (fully)
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MrhError {
+    /// Reported by git
+    Git(Error),
+    /// A directory could not be walked, see [`Crawler::report_walk_errors`]
+    Walk(String),
+    /// The remote did not accept the credentials given
+    RemoteAuth(String),
+    /// The remote took longer than [`Crawler::remote_timeout`]
+    Timeout(Duration),
+}
+
+impl std::fmt::Display for MrhError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Git(why) => why.fmt(f),
+            Self::Walk(why) | Self::RemoteAuth(why) => f.write_str(why),
+            Self::Timeout(timeout) => write!(f, "remote access timed out after {timeout:?}"),
+        }
+    }
+}
+
+impl std::error::Error for MrhError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Git(why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for MrhError {
+    fn from(why: Error) -> Self {
+        Self::Git(why)
+    }
+}
+
 /// Represents Crawler output
 ///
 /// There are 3 possible scenarios:
@@ -53,49 +351,584 @@ use indexmap::set::IndexSet as Set;
 /// - There are no pending states, and there is some error preventing the
 ///   repo from being inspected properly... `error` will have `Some` value
 /// - There are pending states... `pending` will have `Some` value
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Output {
     /// Repository path
     pub path: PathBuf,
+    /// Branch that HEAD points at, unless detached
+    pub branch: Option<String>,
     /// A list of pending actions
-    pub pending: Option<Set<&'static str>>,
-    /// Git-related error
-    pub error: Option<Error>,
+    pub pending: Option<Set<PendingState>>,
+    /// Why the repo could not be inspected (fully)
+    pub error: Option<MrhError>,
+    /// Name of the remote that divergence (unpushed/outdated commits,
+    /// tags, unfetched commits) is reported against,
+    /// or the ref given to [`Crawler::compare_against`]
+    pub remote: Option<String>,
+    /// Host of the remote named by [`Crawler::remote_name`],
+    /// which is "localhost" for remotes that are local paths
+    pub remote_host: Option<String>,
+    /// Untracked or newly-added files that look like secrets,
+    /// see [`Crawler::warn_secrets`]
+    pub secrets: Option<Vec<PathBuf>>,
+    /// How many commits HEAD is ahead of its upstream, if it has one
+    pub ahead: Option<usize>,
+    /// How many commits HEAD is behind its upstream, if it has one
+    pub behind: Option<usize>,
+    /// How many commits HEAD is (ahead, behind) the ref given to
+    /// [`Crawler::since_ref`], if the ref exists in this repo
+    pub since_ref: Option<(usize, usize)>,
+    /// Tag pointing at HEAD, see [`Crawler::show_tag`]
+    pub tag: Option<String>,
+    /// Author of the HEAD commit, see [`Crawler::show_author`]
+    pub last_author: Option<String>,
+    /// Patterns in `.git/info/exclude`, see [`Crawler::local_excludes`]
+    pub local_excludes: Option<Vec<String>>,
+    /// Age of the HEAD commit, going by committer time,
+    /// see [`Crawler::stale_after`]
+    pub head_age: Option<Duration>,
+    /// How many untracked files there are, if any, unless
+    /// [`Crawler::ignore_untracked`] is enabled
+    ///
+    /// An untracked directory counts as one.
+    pub untracked_count: Option<usize>,
+    /// Local branches other than HEAD's with unpushed commits,
+    /// see [`Crawler::check_all_branches`]
+    pub unpushed_branches: Option<Vec<String>>,
+    /// Local branches whose upstream no longer exists,
+    /// see [`Crawler::flag_gone_upstream`]
+    pub gone_upstreams: Option<Vec<String>>,
+}
+
+/// Renders like the CLI does, minus colors and extra details:
+/// `path [branch] @tag (pending, states) (error: why)`
+///
+/// The path is shown as is, so is relative to the root path
+/// (or [`Crawler::relative_to`]), unless [`Crawler::absolute_paths`]
+/// is enabled.
+impl std::fmt::Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(ref branch) = self.branch {
+            write!(f, " [{branch}]")?;
+        }
+        if let Some(ref tag) = self.tag {
+            write!(f, " @{tag}")?;
+        }
+        if let Some(ref pending) = self.pending {
+            let pending: Vec<_> = pending.iter().map(|state| state.as_str()).collect();
+            write!(f, " ({})", pending.join(", "))?;
+        }
+        if let Some(ref error) = self.error {
+            write!(f, " (error: {error})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Shape of serialized [`Output`], bumped whenever a field is changed
+/// or removed (but not when one is added)
+#[cfg(feature = "serde")]
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Paths become strings, states and errors their human-readable forms,
+/// and `head_age` becomes `head_age_secs`
+#[cfg(feature = "serde")]
+impl serde::Serialize for Output {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct SinceRef {
+            ahead: usize,
+            behind: usize,
+        }
+        #[derive(serde::Serialize)]
+        struct Shape<'a> {
+            schema_version: u32,
+            path: std::borrow::Cow<'a, str>,
+            branch: &'a Option<String>,
+            pending: Option<Vec<&'static str>>,
+            error: Option<String>,
+            remote: &'a Option<String>,
+            remote_host: &'a Option<String>,
+            ahead: Option<usize>,
+            behind: Option<usize>,
+            secrets: Option<Vec<std::borrow::Cow<'a, str>>>,
+            since_ref: Option<SinceRef>,
+            tag: &'a Option<String>,
+            last_author: &'a Option<String>,
+            local_excludes: &'a Option<Vec<String>>,
+            head_age_secs: Option<u64>,
+            untracked_count: Option<usize>,
+            unpushed_branches: &'a Option<Vec<String>>,
+            gone_upstreams: &'a Option<Vec<String>>,
+        }
+        Shape {
+            schema_version: SCHEMA_VERSION,
+            path: self.path.to_string_lossy(),
+            branch: &self.branch,
+            pending: self
+                .pending
+                .as_ref()
+                .map(|pending| pending.iter().map(|state| state.as_str()).collect()),
+            error: self.error.as_ref().map(ToString::to_string),
+            remote: &self.remote,
+            remote_host: &self.remote_host,
+            ahead: self.ahead,
+            behind: self.behind,
+            secrets: self
+                .secrets
+                .as_ref()
+                .map(|secrets| secrets.iter().map(|path| path.to_string_lossy()).collect()),
+            since_ref: self
+                .since_ref
+                .map(|(ahead, behind)| SinceRef { ahead, behind }),
+            tag: &self.tag,
+            last_author: &self.last_author,
+            local_excludes: &self.local_excludes,
+            head_age_secs: self.head_age.map(|age| age.as_secs()),
+            untracked_count: self.untracked_count,
+            unpushed_branches: &self.unpushed_branches,
+            gone_upstreams: &self.gone_upstreams,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// File names that [`Crawler::warn_secrets`] considers suspicious by default
+pub const DEFAULT_SECRET_PATTERNS: &[&str] = &[
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    "*.p12",
+    "*.pfx",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "id_rsa",
+    ".netrc",
+    "credentials.json",
+];
+
+/// Commit summary prefixes that [`Crawler::flag_wip`] looks for by default
+pub const DEFAULT_WIP_PATTERNS: &[&str] = &["WIP", "fixup!", "squash!"];
+
+/// Counts across all the repos of a crawl, see [`Crawler::summarize`]
+#[derive(Clone, Debug, Default)]
+pub struct Summary {
+    /// Repos yielded
+    pub total: usize,
+    /// Repos with pending action
+    pub pending: usize,
+    /// Repos that could not be inspected
+    pub errors: usize,
+    /// How many repos are in each pending state
+    pub by_state: std::collections::HashMap<PendingState, usize>,
+}
+
+impl Summary {
+    /// Count `output` in, for those consuming the crawl themselves
+    pub fn record(&mut self, output: &Output) {
+        self.total += 1;
+        self.errors += usize::from(output.error.is_some());
+        if let Some(ref pending) = output.pending {
+            self.pending += 1;
+            for &state in pending {
+                *self.by_state.entry(state).or_default() += 1;
+            }
+        }
+    }
+}
+
+/// Time spent in each phase of a crawl, accumulated across all repos
+///
+/// Only collected when [`Crawler::profile`] is enabled.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Profile {
+    /// Walking the filesystem and opening the repos found
+    pub walk: Duration,
+    /// Inspecting local state (status, branches, tags)
+    pub status: Duration,
+    /// Talking to remotes
+    pub remote: Duration,
+}
+
+thread_local! {
+    // remote time of this thread's repos, so that status time can leave
+    // it out, even while other threads add to `Profile::remote`
+    static REMOTE_TIME: std::cell::Cell<Duration> = const { std::cell::Cell::new(Duration::ZERO) };
+}
+
+/// Bounds how many remotes are talked to at once, see
+/// [`Crawler::remote_concurrency`]
+#[cfg(feature = "parallel")]
+struct RemoteSlots {
+    free: Mutex<usize>,
+    freed: std::sync::Condvar,
+}
+
+#[cfg(feature = "parallel")]
+impl RemoteSlots {
+    fn acquire(&self) -> RemoteSlot<'_> {
+        let mut free = self
+            .free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        while *free == 0 {
+            free = self
+                .freed
+                .wait(free)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+        }
+        *free -= 1;
+        RemoteSlot(self)
+    }
+}
+
+/// Gives the slot back when dropped
+#[cfg(feature = "parallel")]
+struct RemoteSlot<'a>(&'a RemoteSlots);
+
+#[cfg(feature = "parallel")]
+impl Drop for RemoteSlot<'_> {
+    fn drop(&mut self) {
+        *self
+            .0
+            .free
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner()) += 1;
+        self.0.freed.notify_one();
+    }
 }
 
+type ProgressFn = Box<dyn FnMut(&Path) + Send>;
+type WalkErrors = Arc<Mutex<VecDeque<(PathBuf, String)>>>;
+type StatusOptionsFn = Box<dyn Fn(&mut StatusOptions) + Send + Sync>;
+type FilterFn = Box<dyn Fn(&Repository) -> bool + Send + Sync>;
+
 /// Crawls the filesystem, looking for Git repos
 pub struct Crawler {
     pending: bool,
+    only_states: Vec<PendingState>,
+    ignore_states: Vec<PendingState>,
     ignore_untracked: bool,
     ignore_uncommitted_repos: bool,
+    flag_empty: bool,
+    include_ignored: bool,
+    flag_repo_state: bool,
     absolute_paths: bool,
     untagged_heads: bool,
+    dangling_tags: bool,
+    flag_shallow: bool,
+    detached_head: bool,
+    off_default_branch: bool,
+    show_tag: bool,
+    show_author: bool,
+    include_stashed: bool,
+    flag_whitespace: bool,
+    flag_wip: bool,
+    wip_patterns: Vec<String>,
+    require_signed: bool,
+    local_excludes: bool,
+    all_branches: bool,
+    include_submodules: bool,
+    recent_resets: Option<Duration>,
+    stale_after: Option<Duration>,
+    warn_secrets: bool,
+    secret_patterns: GlobSet,
+    since_ref: Option<String>,
+    compare_against: Option<String>,
+    collapse_diverged: bool,
+    combine_changes: bool,
+    local_remotes: bool,
+    flag_no_remote: bool,
+    flag_no_upstream: bool,
+    flag_gone_upstream: bool,
     access_remote: Option<String>,
-    root_path: PathBuf,
-    iter: Box<dyn Iterator<Item = Repository>>,
+    remote_name: String,
+    ssh_key_path: Option<PathBuf>,
+    ssh_passphrase: Option<String>,
+    https_token: Option<String>,
+    https_username: String,
+    all_remotes: bool,
+    fetch: bool,
+    flag_prune: bool,
+    flag_default_renamed: bool,
+    remote_timeout: Option<Duration>,
+    profile: Option<Mutex<Profile>>,
+    // behind a lock, since repos are inspected via `&self`, maybe in parallel
+    on_progress: Option<Mutex<ProgressFn>>,
+    status_options: Option<StatusOptionsFn>,
+    filter: Option<FilterFn>,
+    // shared with the walk, which is not tied to `self`
+    walk_errors: Option<WalkErrors>,
+    #[cfg(feature = "parallel")]
+    threads: usize,
+    #[cfg(feature = "parallel")]
+    remote_slots: Option<RemoteSlots>,
+    #[cfg(feature = "tokio")]
+    async_concurrency: usize,
+    max_depth: Option<usize>,
+    recurse_submodule_like: bool,
+    follow_links: bool,
+    skip_hidden: bool,
+    exclude_dirs: Vec<String>,
+    path_globs: Vec<Glob>,
+    path_matcher: Option<GlobSet>,
+    include_bare: bool,
+    on_branch: Option<String>,
+    path_cache: Option<PathBuf>,
+    // behind a lock, since repos are inspected via `&self`, maybe in parallel
+    #[cfg(feature = "json")]
+    remote_cache: Option<Mutex<remote_cache::RemoteCache>>,
+    root_paths: Vec<PathBuf>,
+    relative_to: Option<PathBuf>,
+    sorted: bool,
+    sorted_outputs: Option<std::vec::IntoIter<Output>>,
+    limit: Option<usize>,
+    yielded: usize,
+    // built on first use, so that builder methods can configure the walk
+    iter: Option<Box<dyn Iterator<Item = PathBuf> + Send + Sync>>,
 }
 
 impl Crawler {
     /// `root` is where crawling for Git repos begin
     pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self::new_multi([root.as_ref().into()])
+    }
+
+    /// Inspect exactly these repo paths, like from a manifest,
+    /// instead of crawling for them
+    ///
+    /// Walk settings, like [`Crawler::max_depth`], have no effect,
+    /// and repo paths are displayed as given.
+    pub fn from_paths<I: IntoIterator<Item = PathBuf>>(paths: I) -> Self {
+        let paths: Vec<_> = paths.into_iter().collect();
+        let mut crawler = Self::new_multi([]);
+        crawler.iter = Some(Box::new(paths.into_iter()));
+        crawler
+    }
+
+    /// Like [`Crawler::new`], but crawling each of `roots` in turn
+    ///
+    /// Repo paths are only made relative when there is a single root,
+    /// since they would otherwise be ambiguous.
+    pub fn new_multi<I: IntoIterator<Item = PathBuf>>(roots: I) -> Self {
         Self {
             pending: false,
+            only_states: Vec::new(),
+            ignore_states: Vec::new(),
             ignore_untracked: false,
             ignore_uncommitted_repos: false,
+            flag_empty: false,
+            include_ignored: false,
+            flag_repo_state: false,
             absolute_paths: false,
             untagged_heads: false,
+            dangling_tags: false,
+            flag_shallow: false,
+            detached_head: false,
+            off_default_branch: false,
+            show_tag: false,
+            show_author: false,
+            include_stashed: false,
+            flag_whitespace: false,
+            flag_wip: false,
+            wip_patterns: DEFAULT_WIP_PATTERNS.iter().map(|&p| p.into()).collect(),
+            require_signed: false,
+            local_excludes: false,
+            all_branches: false,
+            include_submodules: false,
+            recent_resets: None,
+            stale_after: None,
+            warn_secrets: false,
+            secret_patterns: glob_set(DEFAULT_SECRET_PATTERNS.iter().copied()),
+            since_ref: None,
+            compare_against: None,
+            collapse_diverged: false,
+            combine_changes: false,
+            local_remotes: false,
+            flag_no_remote: false,
+            flag_no_upstream: false,
+            flag_gone_upstream: false,
             access_remote: None,
-            root_path: root.as_ref().into(),
-            iter: Box::new(
-                ignore::WalkBuilder::new(root)
-                    .sort_by_file_path(|a, b| a.cmp(b))
-                    .build()
-                    .filter_map(|entry| entry.ok()) // ignore stuff we can't read
-                    .filter(|entry| entry.file_type().is_some())
-                    .filter(|entry| entry.file_type().unwrap().is_dir())
-                    .filter_map(|entry| Repository::open(entry.path()).ok()),
-            ),
+            remote_name: "origin".into(),
+            ssh_key_path: None,
+            ssh_passphrase: None,
+            https_token: None,
+            https_username: "x-access-token".into(),
+            all_remotes: false,
+            fetch: false,
+            flag_prune: false,
+            flag_default_renamed: false,
+            remote_timeout: None,
+            profile: None,
+            on_progress: None,
+            status_options: None,
+            filter: None,
+            walk_errors: None,
+            #[cfg(feature = "parallel")]
+            threads: 0,
+            #[cfg(feature = "parallel")]
+            remote_slots: None,
+            #[cfg(feature = "tokio")]
+            async_concurrency: 8,
+            max_depth: None,
+            recurse_submodule_like: false,
+            follow_links: false,
+            skip_hidden: true,
+            exclude_dirs: Vec::new(),
+            path_globs: Vec::new(),
+            path_matcher: None,
+            include_bare: false,
+            on_branch: None,
+            path_cache: None,
+            #[cfg(feature = "json")]
+            remote_cache: None,
+            root_paths: roots.into_iter().collect(),
+            relative_to: None,
+            sorted: false,
+            sorted_outputs: None,
+            limit: None,
+            yielded: 0,
+            iter: None,
+        }
+    }
+
+    /// Do not descend more than `depth` directories below the root
+    ///
+    /// This is handy when repos are known to live at a fixed depth
+    /// (e.g. `~/code/<org>/<repo>`), to avoid walking big trees inside them.
+    pub const fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Stop after yielding `max` results, like for a quick smoke check
+    ///
+    /// Only yielded results count, so repos left out by
+    /// [`Crawler::pending`] (or similar) do not use up the limit.
+    /// With [`Crawler::sorted`], these are the first `max` results found,
+    /// then sorted, rather than the first `max` of all results.
+    /// [`Crawler::collect_parallel`] stops starting on repos once `max`
+    /// results are in, but those already being inspected on other threads
+    /// still finish, so which `max` results are kept depends on timing.
+    pub const fn limit(mut self, max: usize) -> Self {
+        self.limit = Some(max);
+        self
+    }
+
+    /// Decide if you want to keep looking for repos inside repos
+    ///
+    /// By default, the crawl does not descend into a repo once it's found,
+    /// which saves walking its (possibly huge) working tree. Enable this
+    /// to also find repos checked out inside another repo's working tree,
+    /// like submodules.
+    pub const fn recurse_submodule_like(mut self, answer: bool) -> Self {
+        self.recurse_submodule_like = answer;
+        self
+    }
+
+    /// Decide if you want to find repos through symlinked directories
+    ///
+    /// Such repos are reported at the path the symlink resolves to.
+    pub const fn follow_links(mut self, answer: bool) -> Self {
+        self.follow_links = answer;
+        self
+    }
+
+    /// Decide if you want to skip directories whose names start with a dot,
+    /// like `.cache`, which is the default
+    ///
+    /// Repos under, say, `.config` are then missed, but scanning a home
+    /// directory is much quicker. A hidden root path is scanned regardless.
+    pub const fn skip_hidden(mut self, answer: bool) -> Self {
+        self.skip_hidden = answer;
+        self
+    }
+
+    /// Do not descend into directories whose name matches any of these
+    /// globs, like "node_modules", to speed up the walk
+    ///
+    /// Invalid globs are ignored.
+    pub fn exclude_dirs(mut self, patterns: Vec<String>) -> Self {
+        self.exclude_dirs = patterns;
+        self
+    }
+
+    /// Only inspect repos whose path matches this glob,
+    /// like "*/frontend-*", skipping the status and remote checks of others
+    ///
+    /// The glob is matched against the path as displayed, so is relative
+    /// to the root path, unless [`Crawler::absolute_paths`] is enabled.
+    /// This can be called more than once, to match any of the globs.
+    /// An invalid glob is ignored.
+    pub fn path_glob(mut self, pattern: String) -> Self {
+        if let Ok(glob) = Glob::new(&pattern) {
+            self.path_globs.push(glob);
         }
+        let mut builder = GlobSetBuilder::new();
+        for glob in &self.path_globs {
+            builder.add(glob.clone());
+        }
+        self.path_matcher = builder.build().ok();
+        self
+    }
+
+    /// Decide if you want bare repos, like mirrors, to be inspected too
+    ///
+    /// Having no working tree, they only get the checks that concern
+    /// HEAD and remotes.
+    pub const fn include_bare(mut self, answer: bool) -> Self {
+        self.include_bare = answer;
+        self
+    }
+
+    /// Decide if you want repos yielded in order of their path
+    ///
+    /// The walk is already sorted, but a path can differ from where the
+    /// repo was found (like via [`Crawler::follow_links`]). Sorting means
+    /// nothing is yielded until every repo is inspected, and all results
+    /// are held in memory.
+    pub const fn sorted(mut self, answer: bool) -> Self {
+        self.sorted = answer;
+        self
+    }
+
+    /// Only yield repos whose HEAD is on the branch with this name
+    ///
+    /// Repos with a detached HEAD are on no branch, so are left out.
+    pub fn on_branch(mut self, name: String) -> Self {
+        self.on_branch = Some(name);
+        self
+    }
+
+    /// Remember where repos were found, to skip the walk next time
+    ///
+    /// The `file` also records the modification time of each directory
+    /// walked, and the walk is redone if any of them changed. Checking
+    /// those is cheaper than listing all directories, which helps on
+    /// big trees whose repos rarely come and go.
+    pub fn path_cache(mut self, file: PathBuf) -> Self {
+        self.path_cache = Some(file);
+        self
+    }
+
+    /// Remember what remotes had to say, to skip asking again
+    /// for up to `ttl`, like when polling many repos every few minutes
+    ///
+    /// Results are only reused for a repo whose HEAD has not moved since.
+    /// Other local changes, like new tags, are not noticed until the
+    /// `ttl` runs out. Remote errors are not remembered, and the `file`
+    /// is written when the [`Crawler`] is dropped, or on
+    /// [`Crawler::flush_cache`]. The `file` is JSON, and is only reused
+    /// under the same remote settings, like [`Crawler::remote_name`].
+    /// It only has effect if [`Crawler::access_remote`] is enabled.
+    #[cfg(feature = "json")]
+    pub fn remote_cache(mut self, file: PathBuf, ttl: Duration) -> Self {
+        let cache = remote_cache::RemoteCache::new(file, ttl);
+        self.remote_cache = Some(Mutex::new(cache));
+        self
     }
 
     /// Decide if you only want matches that are in pending state
@@ -104,7 +937,33 @@ impl Crawler {
         self
     }
 
+    /// Only yield repos in at least one of these pending states,
+    /// like just those with [`PendingState::OutdatedBranch`]
+    ///
+    /// Their other states are still reported, and as with
+    /// [`Crawler::pending`], so are repos that could not be inspected.
+    /// An empty list (the default) lets all repos through.
+    pub fn only_states(mut self, states: Vec<PendingState>) -> Self {
+        self.only_states = states;
+        self
+    }
+
+    /// Never report these pending states, like
+    /// [`PendingState::OutdatedBranch`] where branches lag on purpose
+    ///
+    /// A repo left with no other state counts as clean,
+    /// so is left out by [`Crawler::pending`].
+    /// This applies before [`Crawler::only_states`].
+    pub fn ignore_states(mut self, states: Vec<PendingState>) -> Self {
+        self.ignore_states = states;
+        self
+    }
+
     /// Decide if you want to exclude matches that have untracked files
+    ///
+    /// Either way, files that git ignores do not count as untracked,
+    /// including those matching the user's `core.excludesfile`
+    /// (`$XDG_CONFIG_HOME/git/ignore` if unset).
     pub const fn ignore_untracked(mut self, answer: bool) -> Self {
         self.ignore_untracked = answer;
         self
@@ -119,12 +978,48 @@ impl Crawler {
         self
     }
 
+    /// Decide if you want matches that have no commits,
+    /// and no files either, like a `git init` that was never used
+    ///
+    /// These are reported even with [`Crawler::ignore_uncommitted_repos`],
+    /// which then only leaves out those with files.
+    pub const fn flag_empty(mut self, answer: bool) -> Self {
+        self.flag_empty = answer;
+        self
+    }
+
+    /// Decide if you want matches that are in the middle of an operation,
+    /// like a merge or rebase, which needs finishing or aborting
+    pub const fn flag_repo_state(mut self, answer: bool) -> Self {
+        self.flag_repo_state = answer;
+        self
+    }
+
+    /// Decide if you want matches whose working tree has ignored files
+    ///
+    /// Those may take up space, or hint at a misconfigured `.gitignore`.
+    pub const fn include_ignored(mut self, answer: bool) -> Self {
+        self.include_ignored = answer;
+        self
+    }
+
     /// Display absolute paths (instead of relative ones)
     pub const fn absolute_paths(mut self, answer: bool) -> Self {
         self.absolute_paths = answer;
         self
     }
 
+    /// Make repo paths relative to `base`, like the current directory,
+    /// instead of to the root path
+    ///
+    /// This also applies with multiple roots, or with
+    /// [`Crawler::from_paths`], where paths are otherwise left as is.
+    /// Paths outside `base` are left as is.
+    pub fn relative_to(mut self, base: PathBuf) -> Self {
+        self.relative_to = Some(base);
+        self
+    }
+
     /// Decide if you want matches whose HEADS are not tagged
     ///
     /// A use-case is where related repositories (e.g. those comprising
@@ -134,6 +1029,239 @@ impl Crawler {
         self
     }
 
+    /// Decide if you want matches with tags on commits that no branch
+    /// (local or remote-tracking) contains
+    ///
+    /// Such tags are usually left behind by a rebase or force-push.
+    pub const fn flag_dangling_tags(mut self, answer: bool) -> Self {
+        self.dangling_tags = answer;
+        self
+    }
+
+    /// Decide if you want matches that are shallow clones
+    ///
+    /// Their history is cut short, so comparisons with upstream may be
+    /// wrong until a `git fetch --unshallow`.
+    pub const fn flag_shallow(mut self, answer: bool) -> Self {
+        self.flag_shallow = answer;
+        self
+    }
+
+    /// Decide if you want matches whose HEAD is detached
+    ///
+    /// Such repos otherwise look clean, since there is no branch
+    /// to compare against an upstream.
+    pub const fn detached_head(mut self, answer: bool) -> Self {
+        self.detached_head = answer;
+        self
+    }
+
+    /// Decide if you want matches that are not on the remote's default
+    /// branch, like those left checked out on a feature branch
+    ///
+    /// The default branch is what the remote's HEAD points at, as of
+    /// the last fetch, else "main", else "master".
+    /// A detached HEAD counts as being off it.
+    pub const fn off_default_branch(mut self, answer: bool) -> Self {
+        self.off_default_branch = answer;
+        self
+    }
+
+    /// Annotate each repo with the tag pointing at its HEAD, if any
+    ///
+    /// This is informational, so does not count as pending.
+    pub const fn show_tag(mut self, answer: bool) -> Self {
+        self.show_tag = answer;
+        self
+    }
+
+    /// Annotate each repo with the author of its HEAD commit
+    ///
+    /// This is informational, so does not count as pending.
+    pub const fn show_author(mut self, answer: bool) -> Self {
+        self.show_author = answer;
+        self
+    }
+
+    /// Decide if you want matches that have stashed changes
+    pub const fn include_stashed(mut self, answer: bool) -> Self {
+        self.include_stashed = answer;
+        self
+    }
+
+    /// Decide if you want matches whose staged changes add lines with
+    /// whitespace errors, like trailing whitespace
+    ///
+    /// What counts as an error follows `core.whitespace`, as far as
+    /// "blank-at-eol", "space-before-tab", "tab-in-indent",
+    /// and "cr-at-eol" go, which is most of what git checks for.
+    pub const fn flag_whitespace(mut self, answer: bool) -> Self {
+        self.flag_whitespace = answer;
+        self
+    }
+
+    /// Decide if you want matches with unpushed commits that look like
+    /// work in progress, see [`Crawler::wip_patterns`]
+    pub const fn flag_wip(mut self, answer: bool) -> Self {
+        self.flag_wip = answer;
+        self
+    }
+
+    /// Commit summary prefixes that [`Crawler::flag_wip`] looks for,
+    /// replacing [`DEFAULT_WIP_PATTERNS`]
+    pub fn wip_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.wip_patterns = patterns;
+        self
+    }
+
+    /// Decide if you want matches with unpushed commits that are not
+    /// signed (be it with GPG or SSH), for where signing is required
+    ///
+    /// Pushed commits are left alone, since it's too late to sign those.
+    /// Signatures are only looked for, not verified.
+    pub const fn require_signed_commits(mut self, answer: bool) -> Self {
+        self.require_signed = answer;
+        self
+    }
+
+    /// Decide if you want matches that have patterns in `.git/info/exclude`
+    ///
+    /// Those ignore rules are local, so collaborators do not share them.
+    pub const fn local_excludes(mut self, answer: bool) -> Self {
+        self.local_excludes = answer;
+        self
+    }
+
+    /// Decide if you want matches with unpushed commits on local branches
+    /// other than the one checked out
+    ///
+    /// Only branches with an upstream are considered. This costs a graph
+    /// walk per branch, so can be noticeably slower on repos with many
+    /// (or long-lived) branches.
+    pub const fn check_all_branches(mut self, answer: bool) -> Self {
+        self.all_branches = answer;
+        self
+    }
+
+    /// Decide if you want matches whose submodules are modified
+    /// or uninitialized
+    pub const fn include_submodules(mut self, answer: bool) -> Self {
+        self.include_submodules = answer;
+        self
+    }
+
+    /// Decide if you want matches whose HEAD was recently reset
+    ///
+    /// Scans the HEAD reflog for `reset: moving to` entries that are
+    /// younger than `window`, to help find work that may need recovering.
+    /// Note that the reflog does not record the reset mode,
+    /// so soft and mixed resets are reported as well.
+    pub const fn recent_resets(mut self, window: Duration) -> Self {
+        self.recent_resets = Some(window);
+        self
+    }
+
+    /// Decide if you want matches whose HEAD commit is older than `age`,
+    /// going by committer time
+    ///
+    /// The age of HEAD then also becomes part of the output.
+    pub const fn stale_after(mut self, age: Duration) -> Self {
+        self.stale_after = Some(age);
+        self
+    }
+
+    /// Decide if you want matches with untracked or newly-added files
+    /// that look like secrets, see [`Crawler::secret_patterns`]
+    ///
+    /// This is a cheap heuristic, not a scan of file contents.
+    pub const fn warn_secrets(mut self, answer: bool) -> Self {
+        self.warn_secrets = answer;
+        self
+    }
+
+    /// File name globs that [`Crawler::warn_secrets`] looks for,
+    /// replacing [`DEFAULT_SECRET_PATTERNS`]
+    ///
+    /// Invalid patterns are ignored.
+    pub fn secret_patterns(mut self, patterns: Vec<String>) -> Self {
+        self.secret_patterns = glob_set(patterns.iter().map(String::as_str));
+        self
+    }
+
+    /// Report how far HEAD has moved relative to an arbitrary ref
+    ///
+    /// `refname` can be anything `git rev-parse` understands, like a tag
+    /// or a release branch. Repos where it does not resolve are not
+    /// annotated. This is informational, so does not count as pending.
+    pub fn since_ref(mut self, refname: Option<String>) -> Self {
+        self.since_ref = refname;
+        self
+    }
+
+    /// Report unpushed commits and outdated branches against this ref,
+    /// like `origin/main`, instead of the branch's upstream
+    ///
+    /// This helps with branches that have no upstream configured.
+    /// Repos where `refname` does not resolve are not compared.
+    pub fn compare_against(mut self, refname: String) -> Self {
+        self.compare_against = Some(refname);
+        self
+    }
+
+    /// Decide if you want "diverged" reported, in place of both
+    /// "unpushed commits" and "outdated branch"
+    ///
+    /// That makes it clearer that a rebase or merge is needed,
+    /// not just a push or a pull.
+    pub const fn collapse_diverged(mut self, answer: bool) -> Self {
+        self.collapse_diverged = answer;
+        self
+    }
+
+    /// Decide if you want "uncommitted changes" reported, in place of both
+    /// "staged changes" and "unstaged changes"
+    ///
+    /// This matches the output of older versions.
+    pub const fn combine_changes(mut self, answer: bool) -> Self {
+        self.combine_changes = answer;
+        self
+    }
+
+    /// Decide if you want matches with a local-path remote that is missing
+    ///
+    /// This covers `file://` URLs and plain paths (e.g. a clone from
+    /// a USB drive), and does not touch the network.
+    pub const fn local_remotes(mut self, answer: bool) -> Self {
+        self.local_remotes = answer;
+        self
+    }
+
+    /// Decide if you want matches that have no remote at all,
+    /// so their work exists nowhere else
+    pub const fn flag_no_remote(mut self, answer: bool) -> Self {
+        self.flag_no_remote = answer;
+        self
+    }
+
+    /// Decide if you want matches whose branch tracks nothing,
+    /// despite there being a remote, so pushing needs `-u`
+    pub const fn flag_no_upstream(mut self, answer: bool) -> Self {
+        self.flag_no_upstream = answer;
+        self
+    }
+
+    /// Decide if you want matches whose branch tracks a remote branch
+    /// that no longer exists, like one deleted after its PR got merged
+    ///
+    /// Such a branch can usually be deleted. With
+    /// [`Crawler::check_all_branches`], all local branches are checked,
+    /// not just the one checked out. Note that the remote-tracking branch
+    /// is only gone once pruned, like via `git fetch --prune`.
+    pub const fn flag_gone_upstream(mut self, answer: bool) -> Self {
+        self.flag_gone_upstream = answer;
+        self
+    }
+
     /// Allow access to the remote of the repo
     ///
     /// This allows checking if the repo is in sync with its remote counterpart,
@@ -159,144 +1287,839 @@ impl Crawler {
         self
     }
 
+    /// Name of the remote to check, when [`Crawler::access_remote`] is
+    /// enabled
+    ///
+    /// Defaults to "origin". Repos that lack it get reported with an error.
+    pub fn remote_name(mut self, name: String) -> Self {
+        self.remote_name = name;
+        self
+    }
+
+    /// Private key to use when [`Crawler::access_remote`] is "ssh-key"
+    ///
+    /// Otherwise, the first of the usual keys found in `~/.ssh` is used.
+    pub fn ssh_key_path(mut self, path: PathBuf) -> Self {
+        self.ssh_key_path = Some(path);
+        self
+    }
+
+    /// Passphrase of the private key used when [`Crawler::access_remote`]
+    /// is "ssh-key", for keys that are encrypted
+    pub fn ssh_passphrase(mut self, passphrase: String) -> Self {
+        self.ssh_passphrase = Some(passphrase);
+        self
+    }
+
+    /// Token to authenticate with to HTTPS remotes,
+    /// like a GitHub personal access token,
+    /// instead of asking the credential helper
+    ///
+    /// This suits headless setups like CI, where there is no helper.
+    /// The token is never included in any output, and callers are
+    /// responsible for keeping it out of their logs too.
+    pub fn https_token(mut self, token: String) -> Self {
+        self.https_token = Some(token);
+        self
+    }
+
+    /// User name to go along with [`Crawler::https_token`]
+    ///
+    /// Default is "x-access-token", which is what GitHub expects.
+    pub fn https_username(mut self, username: String) -> Self {
+        self.https_username = username;
+        self
+    }
+
+    /// Decide if you want matches whose HEAD is on none of the remotes,
+    /// meaning the work exists nowhere else
+    ///
+    /// This contacts every remote, not just [`Crawler::remote_name`],
+    /// and only has effect if [`Crawler::access_remote`] is enabled.
+    pub const fn all_remotes(mut self, answer: bool) -> Self {
+        self.all_remotes = answer;
+        self
+    }
+
+    /// Decide if you want the remote fetched from before comparing against it
+    ///
+    /// This updates the remote-tracking branches, so the reported
+    /// unpushed commits and outdated branches are current, and later pulls
+    /// are quick. Note that it writes to the repos, unlike everything else,
+    /// and that [`Crawler::remote_timeout`] does not cover it.
+    /// It only has effect if [`Crawler::access_remote`] is enabled.
+    pub const fn do_fetch(mut self, answer: bool) -> Self {
+        self.fetch = answer;
+        self
+    }
+
+    /// Decide if you want matches with remote-tracking branches
+    /// that are gone from the remote, so need `git remote prune`
+    ///
+    /// It only has effect if [`Crawler::access_remote`] is enabled.
+    pub const fn flag_prune(mut self, answer: bool) -> Self {
+        self.flag_prune = answer;
+        self
+    }
+
+    /// Decide if you want matches still tracking the remote's old default
+    /// branch, like `master` after a rename to `main`
+    ///
+    /// It only has effect if [`Crawler::access_remote`] is enabled.
+    pub const fn flag_default_renamed(mut self, answer: bool) -> Self {
+        self.flag_default_renamed = answer;
+        self
+    }
+
+    /// Give up on a repo's remote after this long, reporting an error
+    /// for that repo instead of stalling the crawl
+    ///
+    /// There is no timeout by default.
+    pub const fn remote_timeout(mut self, timeout: Duration) -> Self {
+        self.remote_timeout = Some(timeout);
+        self
+    }
+
+    /// Decide if you want directories that could not be walked,
+    /// like those without read permission, reported
+    ///
+    /// Otherwise they are skipped silently, along with any repos inside.
+    /// Each is reported as an [`Output`] with only `path` and `error` set.
+    /// Note that a walk skipped thanks to [`Crawler::path_cache`]
+    /// reports nothing.
+    pub fn report_walk_errors(mut self, answer: bool) -> Self {
+        self.walk_errors = answer.then(WalkErrors::default);
+        self
+    }
+
+    /// Accumulate time spent in each phase of the crawl
+    ///
+    /// Results are available from [`Crawler::profile_report`]
+    /// once the crawl is done.
+    pub fn profile(mut self, answer: bool) -> Self {
+        self.profile = answer.then(Mutex::default);
+        self
+    }
+
+    /// Call `f` with the path of each repo that is about to be inspected,
+    /// like for showing progress during a long crawl
+    ///
+    /// This happens even for repos that end up not being yielded,
+    /// like those without pending action under [`Crawler::pending`].
+    pub fn on_progress(mut self, f: Box<dyn FnMut(&Path) + Send>) -> Self {
+        self.on_progress = Some(Mutex::new(f));
+        self
+    }
+
+    /// Adjust the options used to get the status of each repo,
+    /// like to limit it to a pathspec
+    ///
+    /// `configure` is applied on top of the defaults,
+    /// so anything it does not touch is unchanged.
+    /// Note that untracked files are still subject to
+    /// [`Crawler::ignore_untracked`], whatever `include_untracked` says,
+    /// and that recursing into untracked dirs is enabled by
+    /// [`Crawler::warn_secrets`].
+    pub fn status_options<F>(mut self, configure: F) -> Self
+    where
+        F: Fn(&mut StatusOptions) + Send + Sync + 'static,
+    {
+        self.status_options = Some(Box::new(configure));
+        self
+    }
+
+    /// Only inspect repos for which `predicate` returns true,
+    /// like those with a remote on a certain host
+    ///
+    /// It runs before the status and remote checks,
+    /// so can also be used to save their cost on repos of no interest.
+    /// Note that this shadows [`Iterator::filter`], which works on outputs.
+    pub fn filter<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Repository) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(predicate));
+        self
+    }
+
+    /// Check if any repo has pending action, stopping at the first one
+    ///
+    /// This saves inspecting (and talking to the remotes of)
+    /// the rest of the repos.
+    /// Repos that could not be inspected do not count, unless some
+    /// pending states were found before the error, like local changes
+    /// in a repo whose remote could not be checked.
+    pub fn has_pending(mut self) -> bool {
+        self.any(|output| output.pending.is_some())
+    }
+
+    /// Write what [`Crawler::remote_cache`] learned so far,
+    /// for when the crawler will not get to be dropped,
+    /// like before calling [`std::process::exit`]
+    #[cfg(feature = "json")]
+    pub fn flush_cache(&self) {
+        if let Some(ref cache) = self.remote_cache {
+            cache
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .save();
+        }
+    }
+
+    /// Drive the crawl to completion, only keeping counts
+    pub fn summarize(self) -> Summary {
+        let mut summary = Summary::default();
+        for output in self {
+            summary.record(&output);
+        }
+        summary
+    }
+
+    /// Time spent so far in each phase, if profiling is enabled
+    pub fn profile_report(&self) -> Option<Profile> {
+        let profile = self.profile.as_ref()?;
+        Some(
+            *profile
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()),
+        )
+    }
+
+    fn record(&self, update: impl FnOnce(&mut Profile)) {
+        if let Some(profile) = &self.profile {
+            update(
+                &mut profile
+                    .lock()
+                    .unwrap_or_else(|poisoned| poisoned.into_inner()),
+            );
+        }
+    }
+
+    /// Drive the crawl, calling `f` with each result as it comes
+    ///
+    /// This is the same as consuming the [`Iterator`], for those that
+    /// prefer a callback, like when accumulating into some outside state.
+    pub fn for_each_output<F: FnMut(Output)>(self, f: F) {
+        self.for_each(f);
+    }
+
+    /// Number of threads used by [`Crawler::collect_parallel`]
+    ///
+    /// The default of 0 lets rayon decide, which usually means one
+    /// per CPU.
+    #[cfg(feature = "parallel")]
+    pub const fn parallel(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Talk to at most `n` remotes at a time in [`Crawler::collect_parallel`],
+    /// however many threads there are
+    ///
+    /// Each repo's remote costs a fresh connection and handshake,
+    /// so a tree with most repos on one host (like github.com) can
+    /// otherwise flood it, and get throttled. Timing is then also more
+    /// predictable: it grows with the number of repos divided by `n`,
+    /// times the round-trip to the host.
+    /// Local inspection is not limited, and 0 is treated as 1.
+    #[cfg(feature = "parallel")]
+    pub fn remote_concurrency(mut self, n: usize) -> Self {
+        self.remote_slots = Some(RemoteSlots {
+            free: Mutex::new(n.max(1)),
+            freed: std::sync::Condvar::new(),
+        });
+        self
+    }
+
+    /// Inspect the repos in parallel, instead of one at a time
+    ///
+    /// The filesystem walk is done upfront, then each repo is opened and
+    /// inspected on a rayon worker thread. This pays off most when
+    /// [`Crawler::access_remote`] is enabled, since most of the time is
+    /// then spent waiting on the network. Results are returned all at
+    /// once, in the same order the [`Iterator`] would yield them.
+    /// With [`Crawler::profile`], time for opening repos, and for status
+    /// and remote phases, is summed across threads, so may exceed
+    /// wall-clock time.
+    #[cfg(feature = "parallel")]
+    pub fn collect_parallel(&mut self) -> Vec<Output> {
+        use rayon::prelude::*;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let start = Instant::now();
+        let paths: Vec<_> = std::iter::from_fn(|| self.next_path()).collect();
+        self.record(|profile| profile.walk += start.elapsed());
+        let max = self.limit.unwrap_or(usize::MAX);
+        let found = AtomicUsize::new(0);
+        let inspect = || {
+            paths
+                .par_iter()
+                .filter_map(|path| {
+                    // no more repos are started once enough are found
+                    if found.load(Ordering::Relaxed) >= max {
+                        return None;
+                    }
+                    let output = self.open_and_inspect(path)?;
+                    found.fetch_add(1, Ordering::Relaxed);
+                    Some(output)
+                })
+                .collect()
+        };
+        let mut outputs: Vec<_> = match rayon::ThreadPoolBuilder::new()
+            .num_threads(self.threads)
+            .build()
+        {
+            Ok(pool) => pool.install(inspect),
+            // fall back to the global pool
+            Err(_) => inspect(),
+        };
+        outputs.extend(std::iter::from_fn(|| self.next_walk_error()));
+        if self.sorted {
+            outputs.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        if let Some(max) = self.limit {
+            outputs.truncate(max);
+        }
+        outputs
+    }
+
+    /// Number of repos inspected at a time by [`Crawler::crawl_async`]
+    ///
+    /// Default is 8, and 0 is treated as 1.
+    #[cfg(feature = "tokio")]
+    pub const fn async_concurrency(mut self, n: usize) -> Self {
+        self.async_concurrency = n;
+        self
+    }
+
+    /// Stream the results, for use from async code, like a server
+    /// that periodically polls remotes
+    ///
+    /// git2 is blocking, so the filesystem walk, and the inspection of
+    /// each repo, is done on tokio's blocking pool, with
+    /// [`Crawler::async_concurrency`] repos inspected at a time.
+    /// Results come in the order inspections finish, regardless of
+    /// [`Crawler::sorted`].
+    ///
+    /// # Panics
+    ///
+    /// This must be called from within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    pub fn crawl_async(mut self) -> impl tokio_stream::Stream<Item = Output> {
+        use tokio_stream::StreamExt;
+
+        let concurrency = self.async_concurrency.max(1);
+        let limit = self.limit.unwrap_or(usize::MAX);
+        let (sender, receiver) = tokio::sync::mpsc::channel(concurrency);
+        tokio::spawn(async move {
+            let walk = tokio::task::spawn_blocking(move || {
+                let paths: Vec<_> = std::iter::from_fn(|| self.next_path()).collect();
+                (self, paths)
+            });
+            let Ok((crawler, paths)) = walk.await else {
+                return;
+            };
+            let crawler = Arc::new(crawler);
+            let slots = Arc::new(tokio::sync::Semaphore::new(concurrency));
+            for path in paths {
+                let Ok(slot) = Arc::clone(&slots).acquire_owned().await else {
+                    return;
+                };
+                let crawler = Arc::clone(&crawler);
+                let sender = sender.clone();
+                tokio::task::spawn_blocking(move || {
+                    let _slot = slot;
+                    let output = crawler.open_and_inspect(&path);
+                    if let Some(output) = output {
+                        // the stream was dropped if this fails
+                        let _ = sender.blocking_send(output);
+                    }
+                });
+            }
+            while let Some(output) = crawler.next_walk_error() {
+                if sender.send(output).await.is_err() {
+                    return;
+                }
+            }
+        });
+        tokio_stream::wrappers::ReceiverStream::new(receiver).take(limit)
+    }
+
+    fn report_progress(&self, path: &Path) {
+        if let Some(ref on_progress) = self.on_progress {
+            (on_progress
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner()))(path);
+        }
+    }
+
     fn repo_ops(&self, repo: &Repository) -> Option<Output> {
+        if self.filter.as_ref().is_some_and(|keep| !keep(repo)) {
+            return None;
+        }
         if let Some(path) = repo.workdir() {
             // ignore libgit2-sys test repos
             if git2::Repository::discover(path).is_err() {
                 return None;
             }
+            if !self.path_matches(path) {
+                return None;
+            }
+            self.report_progress(path);
             let mut pending = Set::new();
             let mut path = path.to_path_buf();
             if !self.absolute_paths {
                 path = self.make_relative(&path);
             }
             let mut opts = StatusOptions::new();
-            opts.include_ignored(false)
+            opts.include_ignored(self.include_ignored)
                 .include_untracked(true)
                 .renames_head_to_index(true)
-                .renames_index_to_workdir(true);
+                .renames_index_to_workdir(true)
+                .recurse_untracked_dirs(self.warn_secrets);
+            if let Some(ref configure) = self.status_options {
+                configure(&mut opts);
+            }
             let local_ref = match repo.head() {
                 Ok(head) => head,
                 Err(why) => {
-                    if self.ignore_uncommitted_repos
-                        && why.class() == git2::ErrorClass::Reference
-                        && why.code() == git2::ErrorCode::UnbornBranch
-                    {
+                    let unborn = why.class() == git2::ErrorClass::Reference
+                        && why.code() == git2::ErrorCode::UnbornBranch;
+                    let empty = unborn && self.flag_empty && is_empty(repo, &mut opts);
+                    if unborn && !empty && self.ignore_uncommitted_repos {
                         return None;
                     }
+                    if let Some(ref name) = self.on_branch {
+                        // HEAD still names the branch that is yet to be born
+                        let head = repo.find_reference("HEAD").ok()?;
+                        let target = head.symbolic_target()?;
+                        if !unborn || target.strip_prefix("refs/heads/") != Some(name) {
+                            return None;
+                        }
+                    }
+                    if empty {
+                        let mut pending = Set::from([PendingState::EmptyRepo]);
+                        pending.retain(|state| !self.ignore_states.contains(state));
+                        if (pending.is_empty() && self.pending) || !self.wanted(&pending) {
+                            return None;
+                        }
+                        return Some(Output {
+                            path,
+                            pending: (!pending.is_empty()).then_some(pending),
+                            ..Default::default()
+                        });
+                    }
                     return Some(Output {
                         path,
-                        pending: None,
-                        error: Some(why),
+                        error: Some(why.into()),
+                        ..Default::default()
                     });
                 }
             };
+            // HEAD can be (oddly) made to point at a remote-tracking ref
+            let head_on_remote = local_ref.is_remote();
             let local_branch = Branch::wrap(local_ref);
-            let local_head_oid = match local_branch.get().target() {
-                Some(oid) => oid,
-                None => return None,
-            };
+            let local_head_oid = local_branch.get().target()?;
+            let branch = local_branch
+                .get()
+                .is_branch()
+                .then(|| local_branch.get().shorthand().map(String::from))
+                .flatten();
+            // a detached HEAD is on no branch, so never matches
+            if self.on_branch.is_some() && branch != self.on_branch {
+                return None;
+            }
             match repo.statuses(Some(&mut opts)) {
                 Ok(statuses) => {
+                    let mut secrets = Vec::new();
+                    let mut untracked_count = 0;
                     for status in statuses.iter() {
                         pending = self.diff_ops(&status, pending);
+                        if !self.ignore_untracked && status.status().is_wt_new() {
+                            untracked_count += 1;
+                        }
+                        if let Some(path) = self.possible_secret(&status) {
+                            secrets.push(path);
+                        }
                     }
-                    if self.untagged_heads {
-                        let local_ref = local_branch.get();
-                        if let Ok(tags) = repo.tag_names(None) {
-                            let mut untagged = true;
-                            for tag in tags.iter().flatten() {
-                                let tag = format!("refs/tags/{tag}");
-                                if let Ok(reference) = repo.find_reference(&tag) {
-                                    if &reference == local_ref {
-                                        untagged = false;
-                                        break;
-                                    }
-                                }
-                            }
-                            if untagged {
-                                pending.insert("untagged HEAD");
+                    if !secrets.is_empty() {
+                        pending.insert(PendingState::PossibleSecrets);
+                    }
+                    // status deltas do not always surface conflicts, the index does
+                    if repo.index().is_ok_and(|index| index.has_conflicts()) {
+                        pending.insert(PendingState::Conflicts);
+                    }
+                    if self.flag_repo_state {
+                        if let Some(state) = operation_in_progress(repo) {
+                            pending.insert(state);
+                        }
+                    }
+                    let tag = if self.untagged_heads || self.show_tag {
+                        head_tag(repo, local_head_oid)
+                    } else {
+                        None
+                    };
+                    if self.untagged_heads && tag.is_none() {
+                        pending.insert(PendingState::UntaggedHead);
+                    }
+                    if self.dangling_tags && has_dangling_tags(repo) {
+                        pending.insert(PendingState::DanglingTags);
+                    }
+                    if self.flag_shallow && repo.is_shallow() {
+                        pending.insert(PendingState::ShallowClone);
+                    }
+                    if self.detached_head && repo.head_detached().unwrap_or(false) {
+                        pending.insert(PendingState::DetachedHead);
+                    }
+                    if self.off_default_branch {
+                        // a detached HEAD is on no branch, so is off it too
+                        if let Some(default) = default_branch(repo, &self.remote_name) {
+                            if branch.as_ref() != Some(&default) {
+                                pending.insert(PendingState::NotOnDefaultBranch);
                             }
                         }
                     }
-                    if let Ok(upstream_branch) = local_branch.upstream() {
-                        let upstream_ref = upstream_branch.into_reference();
-                        let upstream_head_oid = match upstream_ref.target() {
-                            Some(oid) => oid,
-                            None => return None,
-                        };
-                        if local_head_oid != upstream_head_oid {
-                            if let Ok((ahead, behind)) =
-                                repo.graph_ahead_behind(local_head_oid, upstream_head_oid)
-                            {
-                                if ahead > 0 {
-                                    pending.insert("unpushed commits");
-                                }
-                                if behind > 0 {
-                                    pending.insert("outdated branch");
-                                }
+                    if self.flag_whitespace && has_whitespace_errors(repo) {
+                        pending.insert(PendingState::WhitespaceErrors);
+                    }
+                    // git removes this ref once the last stash entry is dropped,
+                    // which spares us the `&mut` needed by `stash_foreach`
+                    if self.include_stashed && repo.find_reference("refs/stash").is_ok() {
+                        pending.insert(PendingState::StashedChanges);
+                    }
+                    let local_excludes = if self.local_excludes {
+                        local_exclude_patterns(repo)
+                    } else {
+                        Vec::new()
+                    };
+                    if !local_excludes.is_empty() {
+                        pending.insert(PendingState::LocalExcludes);
+                    }
+                    let gone_upstreams = if self.flag_gone_upstream {
+                        gone_upstream_branches(repo, self.all_branches)
+                    } else {
+                        Vec::new()
+                    };
+                    if !gone_upstreams.is_empty() {
+                        pending.insert(PendingState::UpstreamGone);
+                    }
+                    let unpushed_branches = if self.all_branches {
+                        unpushed_other_branches(repo, branch.as_deref())
+                    } else {
+                        Vec::new()
+                    };
+                    if !unpushed_branches.is_empty() {
+                        pending.insert(PendingState::UnpushedOtherBranches);
+                    }
+                    if self.include_submodules {
+                        pending = submodule_ops(repo, pending);
+                    }
+                    if self.flag_no_remote && !has_remote(repo) {
+                        pending.insert(PendingState::NoRemote);
+                    }
+                    // a detached HEAD has no branch to track anything
+                    if self.flag_no_upstream
+                        && branch.is_some()
+                        && has_remote(repo)
+                        && local_branch.upstream().is_err()
+                    {
+                        pending.insert(PendingState::NoUpstream);
+                    }
+                    if self.local_remotes && has_missing_local_remote(repo) {
+                        pending.insert(PendingState::RemotePathMissing);
+                    }
+                    if let Some(window) = self.recent_resets {
+                        if has_recent_reset(repo, window) {
+                            pending.insert(PendingState::RecentHardReset);
+                        }
+                    }
+                    let head_age = self.stale_ops(repo, local_head_oid, &mut pending);
+                    if self.fetch && self.access_remote.is_some() {
+                        if let Err(why) = self.fetch_ops(repo) {
+                            return Some(Output {
+                                path,
+                                error: Some(why),
+                                ..Default::default()
+                            });
+                        }
+                    }
+                    let mut remote = None;
+                    let mut ahead_behind = None;
+                    if head_on_remote {
+                        pending.insert(PendingState::HeadOnRemoteTrackingRef);
+                    } else if let Some(ref refname) = self.compare_against {
+                        // repos that lack the ref are simply not compared
+                        let target = repo
+                            .revparse_single(refname)
+                            .and_then(|object| object.peel_to_commit());
+                        if let Ok(target) = target {
+                            ahead_behind = self.ahead_behind_ops(
+                                repo,
+                                &mut pending,
+                                local_head_oid,
+                                target.id(),
+                            );
+                            if ahead_behind.is_some_and(|counts| counts != (0, 0)) {
+                                remote = Some(refname.clone());
                             }
                         }
+                    } else if let Ok(upstream_branch) = local_branch.upstream() {
+                        let upstream_ref = upstream_branch.into_reference();
+                        if upstream_ref.is_branch() {
+                            // ahead/behind is against a local branch, not a remote
+                            pending.insert(PendingState::UpstreamIsLocalBranch);
+                        }
+                        let upstream_head_oid = upstream_ref.target()?;
+                        ahead_behind = self.ahead_behind_ops(
+                            repo,
+                            &mut pending,
+                            local_head_oid,
+                            upstream_head_oid,
+                        );
+                        if ahead_behind.is_some_and(|counts| counts != (0, 0)) {
+                            remote = upstream_remote_name(repo, &local_branch);
+                        }
                     }
                     if self.access_remote.is_some() {
-                        pending = match self.remote_ops(repo, pending, local_head_oid) {
+                        let local = pending.clone();
+                        let result =
+                            self.timed_remote_ops(repo, pending, local_head_oid, &mut remote);
+                        pending = match result {
                             Ok(pending) => pending,
                             Err(why) => {
-                                return Some(Output {
-                                    path,
-                                    pending: None,
-                                    error: Some(why),
-                                });
+                                return Some(self.remote_error(path, branch, local, why));
                             }
                         }
                     }
-                    if !pending.is_empty() {
-                        Some(Output {
-                            path,
-                            pending: Some(pending),
-                            error: None,
-                        })
-                    } else if self.pending {
-                        None
-                    } else {
-                        Some(Output {
-                            path,
-                            pending: None,
-                            error: None,
-                        })
+                    pending.retain(|state| !self.ignore_states.contains(state));
+                    if (pending.is_empty() && self.pending) || !self.wanted(&pending) {
+                        return None;
                     }
+                    let since_ref = self
+                        .since_ref
+                        .as_ref()
+                        .and_then(|refname| ahead_behind_ref(repo, local_head_oid, refname));
+                    Some(Output {
+                        path,
+                        branch,
+                        pending: (!pending.is_empty()).then_some(pending),
+                        remote,
+                        remote_host: remote_host(repo, &self.remote_name),
+                        ahead: ahead_behind.map(|(ahead, _)| ahead),
+                        behind: ahead_behind.map(|(_, behind)| behind),
+                        secrets: (!secrets.is_empty()).then_some(secrets),
+                        since_ref,
+                        tag: tag.filter(|_| self.show_tag),
+                        last_author: if self.show_author {
+                            head_author(repo, local_head_oid)
+                        } else {
+                            None
+                        },
+                        local_excludes: (!local_excludes.is_empty()).then_some(local_excludes),
+                        head_age,
+                        untracked_count: (untracked_count > 0).then_some(untracked_count),
+                        unpushed_branches: (!unpushed_branches.is_empty())
+                            .then_some(unpushed_branches),
+                        gone_upstreams: (!gone_upstreams.is_empty()).then_some(gone_upstreams),
+                        ..Default::default()
+                    })
                 }
                 Err(why) => Some(Output {
                     path,
-                    pending: None,
-                    error: Some(why),
+                    error: Some(why.into()),
+                    ..Default::default()
                 }),
             }
+        } else if self.include_bare {
+            self.bare_ops(repo)
         } else {
             None
         }
     }
 
-    fn diff_ops<'b>(
+    // Without a working tree, only the checks against HEAD and remotes apply
+    fn bare_ops(&self, repo: &Repository) -> Option<Output> {
+        if !self.path_matches(repo.path()) {
+            return None;
+        }
+        self.report_progress(repo.path());
+        let mut path = repo.path().to_path_buf();
+        if !self.absolute_paths {
+            path = self.make_relative(&path);
+        }
+        let local_head_oid = match repo.head().and_then(|head| head.peel_to_commit()) {
+            Ok(commit) => commit.id(),
+            Err(why) => {
+                if self.ignore_uncommitted_repos && why.code() == git2::ErrorCode::UnbornBranch {
+                    return None;
+                }
+                return Some(Output {
+                    path,
+                    error: Some(why.into()),
+                    ..Default::default()
+                });
+            }
+        };
+        let mut pending = Set::new();
+        let tag = if self.untagged_heads || self.show_tag {
+            head_tag(repo, local_head_oid)
+        } else {
+            None
+        };
+        if self.untagged_heads && tag.is_none() {
+            pending.insert(PendingState::UntaggedHead);
+        }
+        if self.dangling_tags && has_dangling_tags(repo) {
+            pending.insert(PendingState::DanglingTags);
+        }
+        if self.flag_shallow && repo.is_shallow() {
+            pending.insert(PendingState::ShallowClone);
+        }
+        if self.flag_no_remote && !has_remote(repo) {
+            pending.insert(PendingState::NoRemote);
+        }
+        let head_age = self.stale_ops(repo, local_head_oid, &mut pending);
+        let mut remote = None;
+        if self.access_remote.is_some() {
+            let local = pending.clone();
+            let result = self.timed_remote_ops(repo, pending, local_head_oid, &mut remote);
+            pending = match result {
+                Ok(pending) => pending,
+                Err(why) => return Some(self.remote_error(path, None, local, why)),
+            }
+        }
+        pending.retain(|state| !self.ignore_states.contains(state));
+        if (pending.is_empty() && self.pending) || !self.wanted(&pending) {
+            return None;
+        }
+        Some(Output {
+            path,
+            pending: (!pending.is_empty()).then_some(pending),
+            remote,
+            remote_host: remote_host(repo, &self.remote_name),
+            tag: tag.filter(|_| self.show_tag),
+            last_author: if self.show_author {
+                head_author(repo, local_head_oid)
+            } else {
+                None
+            },
+            head_age,
+            ..Default::default()
+        })
+    }
+
+    // What was found locally is still worth reporting when the remote fails
+    fn remote_error(
+        &self,
+        path: PathBuf,
+        branch: Option<String>,
+        mut pending: Set<PendingState>,
+        why: MrhError,
+    ) -> Output {
+        pending.retain(|state| !self.ignore_states.contains(state));
+        Output {
+            path,
+            branch,
+            pending: (!pending.is_empty()).then_some(pending),
+            error: Some(why),
+            ..Default::default()
+        }
+    }
+
+    fn ahead_behind_ops(
+        &self,
+        repo: &Repository,
+        pending: &mut Set<PendingState>,
+        local_head_oid: git2::Oid,
+        upstream_head_oid: git2::Oid,
+    ) -> Option<(usize, usize)> {
+        let (ahead, behind) = if local_head_oid == upstream_head_oid {
+            (0, 0)
+        } else {
+            repo.graph_ahead_behind(local_head_oid, upstream_head_oid)
+                .ok()?
+        };
+        if self.collapse_diverged && ahead > 0 && behind > 0 {
+            pending.insert(PendingState::BranchDiverged);
+        } else {
+            if ahead > 0 {
+                pending.insert(PendingState::UnpushedCommits);
+            }
+            if behind > 0 {
+                pending.insert(PendingState::OutdatedBranch);
+            }
+        }
+        if ahead > 0
+            && self.flag_wip
+            && self.has_wip_commit(repo, local_head_oid, upstream_head_oid)
+        {
+            pending.insert(PendingState::WipCommits);
+        }
+        if ahead > 0
+            && self.require_signed
+            && unpushed_commits(repo, local_head_oid, upstream_head_oid)
+                .any(|commit| repo.extract_signature(&commit.id(), None).is_err())
+        {
+            pending.insert(PendingState::UnsignedCommits);
+        }
+        Some((ahead, behind))
+    }
+
+    // Returns the age of HEAD, when staleness is checked at all
+    fn stale_ops(
+        &self,
+        repo: &Repository,
+        local_head_oid: git2::Oid,
+        pending: &mut Set<PendingState>,
+    ) -> Option<Duration> {
+        let threshold = self.stale_after?;
+        let age = head_age(repo, local_head_oid)?;
+        if age > threshold {
+            pending.insert(PendingState::Stale);
+        }
+        Some(age)
+    }
+
+    // Only unpushed commits are looked at, since published ones are too late
+    fn has_wip_commit(&self, repo: &Repository, head: git2::Oid, upstream: git2::Oid) -> bool {
+        unpushed_commits(repo, head, upstream).any(|commit| {
+            let summary = commit.summary().unwrap_or_default();
+            self.wip_patterns
+                .iter()
+                .any(|pattern| summary.starts_with(pattern.as_str()))
+        })
+    }
+
+    fn diff_ops(
         &self,
         status: &git2::StatusEntry<'_>,
-        mut pending: Set<&'b str>,
-    ) -> Set<&'b str> {
+        mut pending: Set<PendingState>,
+    ) -> Set<PendingState> {
         if let Some(diff_delta) = status.index_to_workdir() {
             match diff_delta.status() {
-                Delta::Untracked => {
-                    if !self.ignore_untracked {
-                        pending.insert("untracked files");
-                    }
+                Delta::Untracked if !self.ignore_untracked => {
+                    pending.insert(PendingState::UntrackedFiles);
+                }
+                Delta::Ignored => {
+                    pending.insert(PendingState::IgnoredFiles);
+                }
+                Delta::Modified if gitlink_changed(&diff_delta) => {
+                    pending.insert(PendingState::SubmodulePointerChanged);
+                }
+                Delta::Modified if self.combine_changes => {
+                    pending.insert(PendingState::UncommittedChanges);
                 }
                 Delta::Modified => {
-                    pending.insert("uncommitted changes");
+                    pending.insert(PendingState::UnstagedChanges);
                 }
                 Delta::Deleted => {
-                    pending.insert("deleted files");
+                    pending.insert(PendingState::DeletedFiles);
                 }
                 Delta::Renamed => {
-                    pending.insert("renamed files");
+                    pending.insert(PendingState::RenamedFiles);
+                }
+                Delta::Conflicted => {
+                    pending.insert(PendingState::Conflicts);
                 }
                 _ => (),
             }
@@ -304,16 +2127,25 @@ impl Crawler {
         if let Some(diff_delta) = status.head_to_index() {
             match diff_delta.status() {
                 Delta::Added => {
-                    pending.insert("added files");
+                    pending.insert(PendingState::AddedFiles);
+                }
+                Delta::Modified if gitlink_changed(&diff_delta) => {
+                    pending.insert(PendingState::SubmodulePointerChanged);
+                }
+                Delta::Modified if self.combine_changes => {
+                    pending.insert(PendingState::UncommittedChanges);
                 }
                 Delta::Modified => {
-                    pending.insert("uncommitted changes");
+                    pending.insert(PendingState::StagedChanges);
                 }
                 Delta::Deleted => {
-                    pending.insert("deleted files");
+                    pending.insert(PendingState::DeletedFiles);
                 }
                 Delta::Renamed => {
-                    pending.insert("renamed files");
+                    pending.insert(PendingState::RenamedFiles);
+                }
+                Delta::Conflicted => {
+                    pending.insert(PendingState::Conflicts);
                 }
                 _ => (),
             }
@@ -321,126 +2153,1119 @@ impl Crawler {
         pending
     }
 
-    fn remote_ops<'b>(
+    fn possible_secret(&self, status: &git2::StatusEntry<'_>) -> Option<PathBuf> {
+        if !self.warn_secrets {
+            return None;
+        }
+        let patterns = &self.secret_patterns;
+        if !status
+            .status()
+            .intersects(git2::Status::WT_NEW | git2::Status::INDEX_NEW)
+        {
+            return None;
+        }
+        let path = Path::new(status.path()?);
+        patterns
+            .is_match(path.file_name()?)
+            .then(|| path.to_path_buf())
+    }
+
+    // Profiles `remote_ops`, and gives up on it after `remote_timeout`
+    fn timed_remote_ops(
         &self,
         repo: &Repository,
-        mut pending: Set<&'b str>,
+        pending: Set<PendingState>,
         local_head_oid: git2::Oid,
-    ) -> Result<Set<&'b str>, Error> {
-        if let Ok(remote) = repo.find_remote("origin") {
-            // XXX howto avoid the following panic
-            let config = git2::Config::open_default().expect("could not get git config");
-            let url = match remote.url() {
-                Some(url) => url,
-                // XXX should not ignore this one, though it seems not a likely one to occur
-                None => return Ok(pending),
-            };
-            let mut callbacks = git2::RemoteCallbacks::new();
-            if url.starts_with("http") {
-                callbacks.credentials(|_, _, _| git2::Cred::credential_helper(&config, url, None));
-            } else if url.starts_with("git") {
-                // github, bitbucket, and gitlab use "git" as ssh username
-                if let Some(ref method) = self.access_remote {
-                    if method == "ssh-key" {
-                        for file_name in &[
-                            "id_dsa",
-                            "id_ecdsa",
-                            "id_ecdsa_sk",
-                            "id_ed25519",
-                            "id_ed25519_sk",
-                            "id_rsa",
-                        ] {
-                            if let Some(home_dir) = dirs::home_dir() {
-                                let private_key = home_dir.join(".ssh").join(file_name);
-                                if private_key.exists() {
-                                    callbacks.credentials(move |_, _, _| {
-                                        git2::Cred::ssh_key("git", None, &private_key, None)
-                                    });
+        divergent_remote: &mut Option<String>,
+    ) -> Result<Set<PendingState>, MrhError> {
+        #[cfg(feature = "json")]
+        if let Some(ref cache) = self.remote_cache {
+            return self.cached_remote_ops(cache, repo, pending, local_head_oid, divergent_remote);
+        }
+        self.uncached_remote_ops(repo, pending, local_head_oid, divergent_remote)
+    }
+
+    #[cfg(feature = "json")]
+    fn cached_remote_ops(
+        &self,
+        cache: &Mutex<remote_cache::RemoteCache>,
+        repo: &Repository,
+        pending: Set<PendingState>,
+        local_head_oid: git2::Oid,
+        divergent_remote: &mut Option<String>,
+    ) -> Result<Set<PendingState>, MrhError> {
+        // results under other settings are no good
+        let key = format!(
+            "{:?} {} {} {} {}",
+            self.access_remote,
+            self.remote_name,
+            self.all_remotes,
+            self.flag_prune,
+            self.flag_default_renamed
+        );
+        let cached = cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(&key, repo.path(), local_head_oid);
+        if let Some(entry) = cached {
+            let mut pending = pending;
+            pending.extend(entry.states);
+            *divergent_remote = divergent_remote.take().or(entry.remote);
+            return Ok(pending);
+        }
+        let before = pending.clone();
+        let had_remote = divergent_remote.is_some();
+        let pending = self.uncached_remote_ops(repo, pending, local_head_oid, divergent_remote)?;
+        let entry = remote_cache::Entry {
+            states: pending.difference(&before).copied().collect(),
+            remote: divergent_remote.clone().filter(|_| !had_remote),
+        };
+        cache
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(&key, repo.path(), local_head_oid, entry);
+        Ok(pending)
+    }
+
+    fn uncached_remote_ops(
+        &self,
+        repo: &Repository,
+        pending: Set<PendingState>,
+        local_head_oid: git2::Oid,
+        divergent_remote: &mut Option<String>,
+    ) -> Result<Set<PendingState>, MrhError> {
+        #[cfg(feature = "parallel")]
+        let _slot = self.remote_slots.as_ref().map(RemoteSlots::acquire);
+        let start = self.profile.as_ref().map(|_| Instant::now());
+        let result = match self.remote_timeout {
+            Some(timeout) => self
+                .remote_ops_within(timeout, repo, pending, local_head_oid)
+                .map(|(pending, remote)| {
+                    *divergent_remote = divergent_remote.take().or(remote);
+                    pending
+                }),
+            None => self
+                .remote_ops(repo, pending, local_head_oid, divergent_remote)
+                .map_err(remote_error),
+        };
+        if let Some(start) = start {
+            let elapsed = start.elapsed();
+            REMOTE_TIME.set(REMOTE_TIME.get() + elapsed);
+            self.record(|profile| profile.remote += elapsed);
+        }
+        result
+    }
+
+    // git2 has no connect timeout, so the remote is accessed on a thread
+    // of its own, which is abandoned (rather than waited on) if it's late
+    fn remote_ops_within(
+        &self,
+        timeout: Duration,
+        repo: &Repository,
+        pending: Set<PendingState>,
+        local_head_oid: git2::Oid,
+    ) -> Result<(Set<PendingState>, Option<String>), MrhError> {
+        let mut crawler = Crawler::new_multi(self.root_paths.clone())
+            .access_remote(self.access_remote.clone())
+            .remote_name(self.remote_name.clone())
+            .all_remotes(self.all_remotes)
+            .flag_prune(self.flag_prune)
+            .flag_default_renamed(self.flag_default_renamed);
+        crawler.ssh_key_path.clone_from(&self.ssh_key_path);
+        crawler.ssh_passphrase.clone_from(&self.ssh_passphrase);
+        crawler.https_token.clone_from(&self.https_token);
+        crawler.https_username.clone_from(&self.https_username);
+        let git_dir = repo.path().to_path_buf();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let result = Repository::open(git_dir).and_then(|repo| {
+                let mut remote = None;
+                let pending = crawler.remote_ops(&repo, pending, local_head_oid, &mut remote)?;
+                Ok((pending, remote))
+            });
+            // nobody is listening anymore if this is too late
+            let _ = sender.send(result);
+        });
+        match receiver.recv_timeout(timeout) {
+            Ok(result) => result.map_err(remote_error),
+            Err(_) => Err(MrhError::Timeout(timeout)),
+        }
+    }
+
+    fn remote_ops(
+        &self,
+        repo: &Repository,
+        mut pending: Set<PendingState>,
+        local_head_oid: git2::Oid,
+        divergent_remote: &mut Option<String>,
+    ) -> Result<Set<PendingState>, Error> {
+        // asking for remote access, but not getting it, deserves an error
+        let remote = repo.find_remote(&self.remote_name)?;
+        let pending_before = pending.len();
+        // avoid "cannot borrow immutable local variable `remote` as mutable"
+        let mut remote = remote.clone();
+        if !self.connect(&mut remote)? {
+            // XXX should not ignore this one, though it seems not a likely one to occur
+            return Ok(pending);
+        }
+        let mut remote_tags = Set::new();
+        if let Ok(remote_list) = remote.list() {
+            for item in remote_list {
+                let name = item.name();
+                if name.starts_with("refs/tags/") {
+                    // This weirdness of a postfix appears on some remote tags
+                    if !name.ends_with("^{}") {
+                        remote_tags.insert((item.name().to_string(), item.oid()));
+                    }
+                } else if name.starts_with("refs/heads") && item.oid() != local_head_oid {
+                    let mut found = false;
+                    if let Ok(branches) = repo.branches(None) {
+                        for branch in branches.flatten() {
+                            if let Some(oid) = branch.0.get().target() {
+                                if oid == item.oid() {
+                                    found = true;
                                     break;
                                 }
                             }
                         }
-                    } else if method == "ssh-agent" {
-                        callbacks.credentials(|_, _, _| git2::Cred::ssh_key_from_agent("git"));
+                    }
+                    if !found {
+                        pending.insert(PendingState::UnfetchedCommits);
                     }
                 }
             }
-            // avoid "cannot borrow immutable local variable `remote` as mutable"
-            let mut remote = remote.clone();
-            remote.connect_auth(git2::Direction::Fetch, Some(callbacks), None)?;
-            let mut remote_tags = Set::new();
-            if let Ok(remote_list) = remote.list() {
-                for item in remote_list {
-                    let name = item.name();
-                    if name.starts_with("refs/tags/") {
-                        // This weirdness of a postfix appears on some remote tags
-                        if !name.ends_with("^{}") {
-                            remote_tags.insert((item.name().to_string(), item.oid()));
-                        }
-                    } else if name.starts_with("refs/heads") && item.oid() != local_head_oid {
-                        let mut found = false;
-                        if let Ok(branches) = repo.branches(None) {
-                            for branch in branches.flatten() {
-                                if let Some(oid) = branch.0.get().target() {
-                                    if oid == item.oid() {
-                                        found = true;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                        if !found {
-                            pending.insert("unfetched commits");
-                        }
-                    }
+            let mut local_tags = Set::new();
+            for (tag, reference) in tag_refs(repo) {
+                if let Some(oid) = reference.target() {
+                    local_tags.insert((format!("refs/tags/{tag}"), oid));
                 }
-                let mut local_tags = Set::new();
-                if let Ok(tags) = repo.tag_names(None) {
-                    for tag in tags.iter().flatten() {
-                        let tag = format!("refs/tags/{tag}");
-                        if let Ok(reference) = repo.find_reference(&tag) {
-                            if let Some(oid) = reference.target() {
-                                local_tags.insert((tag, oid));
-                            }
+            }
+            if !local_tags.is_subset(&remote_tags) {
+                pending.insert(PendingState::UnpushedTags);
+            }
+            if !remote_tags.is_subset(&local_tags) {
+                pending.insert(PendingState::UnpulledTags);
+            }
+            if default_branch_diverged(repo, remote_list) {
+                pending.insert(PendingState::CantFastForward);
+            }
+            if self.flag_default_renamed
+                && default_branch_renamed(repo, remote.name().unwrap_or_default(), remote_list)
+            {
+                pending.insert(PendingState::DefaultBranchRenamed);
+            }
+            if self.flag_prune {
+                let remote_heads: Set<_> = remote_list.iter().map(|head| head.name()).collect();
+                if needs_prune(repo, remote.name().unwrap_or_default(), &remote_heads) {
+                    pending.insert(PendingState::PruneNeeded);
+                }
+            }
+            if self.all_remotes && !head_on_any_remote(repo, local_head_oid, remote_list) {
+                pending = self.other_remotes_ops(repo, pending, local_head_oid);
+            }
+        }
+        if pending.len() > pending_before && divergent_remote.is_none() {
+            *divergent_remote = remote.name().map(String::from);
+        }
+        Ok(pending)
+    }
+
+    // Checks the remotes other than the main one, which is already known
+    // not to have HEAD; any that can't be reached might, so no verdict then
+    fn other_remotes_ops(
+        &self,
+        repo: &Repository,
+        mut pending: Set<PendingState>,
+        local_head_oid: git2::Oid,
+    ) -> Set<PendingState> {
+        let Ok(names) = repo.remotes() else {
+            return pending;
+        };
+        for name in names.iter().flatten() {
+            if name == self.remote_name {
+                continue;
+            }
+            let Ok(mut remote) = repo.find_remote(name) else {
+                return pending;
+            };
+            if !self.connect(&mut remote).unwrap_or(false) {
+                return pending;
+            }
+            match remote.list() {
+                Ok(remote_list) if !head_on_any_remote(repo, local_head_oid, remote_list) => {}
+                _ => return pending,
+            }
+        }
+        pending.insert(PendingState::UnpushedToAllRemotes);
+        pending
+    }
+
+    fn fetch_ops(&self, repo: &Repository) -> Result<(), MrhError> {
+        let mut remote = repo.find_remote(&self.remote_name)?;
+        let Some(url) = remote.url() else {
+            return Ok(());
+        };
+        let mut options = git2::FetchOptions::new();
+        options.remote_callbacks(self.callbacks(url)?);
+        // the configured refspecs, like `git fetch` with no arguments
+        remote
+            .fetch(&[] as &[&str], Some(&mut options), None)
+            .map_err(remote_error)
+    }
+
+    // Returns false if the remote has no URL
+    fn connect(&self, remote: &mut git2::Remote) -> Result<bool, Error> {
+        let Some(url) = remote.url() else {
+            return Ok(false);
+        };
+        let callbacks = self.callbacks(url)?;
+        remote.connect_auth(git2::Direction::Fetch, Some(callbacks), None)?;
+        Ok(true)
+    }
+
+    fn callbacks(&self, url: &str) -> Result<git2::RemoteCallbacks<'static>, Error> {
+        let config = git2::Config::open_default()?;
+        let url = url.to_string();
+        let mut callbacks = git2::RemoteCallbacks::new();
+        if url.starts_with("http") {
+            match self.https_token.clone() {
+                Some(token) => {
+                    let username = self.https_username.clone();
+                    // libgit2 keeps asking for as long as it's rejected
+                    let mut tried = false;
+                    callbacks.credentials(move |_, _, _| {
+                        if std::mem::replace(&mut tried, true) {
+                            return Err(Error::from_str("HTTPS token was rejected"));
                         }
+                        git2::Cred::userpass_plaintext(&username, &token)
+                    });
+                }
+                None => {
+                    callbacks.credentials(move |_, _, _| {
+                        git2::Cred::credential_helper(&config, &url, None)
+                    });
+                }
+            }
+        } else if url.starts_with("git") {
+            // github, bitbucket, and gitlab use "git" as ssh username
+            if let Some(ref method) = self.access_remote {
+                if method == "ssh-key" {
+                    let private_key = self.ssh_key_path.clone().or_else(|| {
+                        let ssh_dir = dirs::home_dir()?.join(".ssh");
+                        [
+                            "id_dsa",
+                            "id_ecdsa",
+                            "id_ecdsa_sk",
+                            "id_ed25519",
+                            "id_ed25519_sk",
+                            "id_rsa",
+                        ]
+                        .iter()
+                        .map(|file_name| ssh_dir.join(file_name))
+                        .find(|private_key| private_key.exists())
+                    });
+                    if let Some(private_key) = private_key {
+                        let passphrase = self.ssh_passphrase.clone();
+                        callbacks.credentials(move |_, _, _| {
+                            git2::Cred::ssh_key("git", None, &private_key, passphrase.as_deref())
+                        });
                     }
+                } else if method == "ssh-agent" {
+                    callbacks.credentials(|_, _, _| git2::Cred::ssh_key_from_agent("git"));
+                }
+            }
+        }
+        Ok(callbacks)
+    }
+
+    fn next_path(&mut self) -> Option<PathBuf> {
+        if self.iter.is_none() {
+            self.iter = Some(self.walk());
+        }
+        self.iter.as_mut()?.next()
+    }
+
+    fn walk(&self) -> Box<dyn Iterator<Item = PathBuf> + Send + Sync> {
+        let recurse = self.recurse_submodule_like;
+        let mut builder = GlobSetBuilder::new();
+        for glob in self
+            .exclude_dirs
+            .iter()
+            .flat_map(|pattern| Glob::new(pattern))
+        {
+            builder.add(glob);
+        }
+        let excluded = builder.build().unwrap_or_else(|_| GlobSet::empty());
+        let mut roots = self.root_paths.iter();
+        let Some(first) = roots.next() else {
+            return Box::new(std::iter::empty());
+        };
+        let mut worktrees = std::collections::HashSet::new();
+        let walk_errors = self.walk_errors.clone();
+        let mut walker = ignore::WalkBuilder::new(first);
+        for root in roots {
+            walker.add(root);
+        }
+        let walk = walker
+            .max_depth(self.max_depth)
+            // loops back into an ancestor are detected, and skipped as errors
+            .follow_links(self.follow_links)
+            .hidden(self.skip_hidden)
+            .filter_entry(move |entry| {
+                // the repo is found at its worktree, so no need to look inside
+                !is_git_dir(entry)
+                    && (recurse || !is_inside_repo(entry))
+                    && !is_excluded(entry, &excluded)
+            })
+            .sort_by_file_path(|a, b| a.cmp(b))
+            .build()
+            .inspect(move |entry| {
+                if let (Err(why), Some(ref errors)) = (entry, &walk_errors) {
+                    let path = walk_error_path(why).unwrap_or_default();
+                    errors
+                        .lock()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner())
+                        .push_back((path, why.to_string()));
                 }
-                if !local_tags.is_subset(&remote_tags) {
-                    pending.insert("unpushed tags");
+            })
+            .filter_map(|entry| entry.ok()) // ignore stuff we can't read
+            .filter(|entry| entry.file_type().is_some())
+            .filter(|entry| entry.file_type().unwrap().is_dir())
+            .map(ignore::DirEntry::into_path)
+            .flat_map(move |path| {
+                // the walk may find them too, like with `recurse`
+                if worktrees.remove(&path) {
+                    return Vec::new();
                 }
-                if !remote_tags.is_subset(&local_tags) {
-                    pending.insert("unpulled tags");
+                let nested = nested_worktrees(&path);
+                worktrees.extend(nested.iter().cloned());
+                std::iter::once(path).chain(nested).collect()
+            });
+        match self.path_cache {
+            Some(ref file) => {
+                // a cache made with different walk settings is no good
+                let key = format!(
+                    "{:?} {:?} {recurse} {} {} {:?}",
+                    self.root_paths,
+                    self.max_depth,
+                    self.follow_links,
+                    self.skip_hidden,
+                    self.exclude_dirs
+                );
+                match path_cache::load(file, &key) {
+                    Some(repos) => Box::new(repos.into_iter()),
+                    None => Box::new(path_cache::Recorder::new(walk, file, key)),
                 }
             }
+            None => Box::new(walk),
         }
-        Ok(pending)
     }
 
-    fn make_relative(&self, target_dir: &Path) -> PathBuf {
-        if let Ok(path) = target_dir.strip_prefix(&self.root_path) {
-            if path.to_string_lossy().is_empty() {
-                ".".into()
-            } else {
-                path.into()
+    fn next_output(&mut self) -> Option<Output> {
+        if self.limit.is_some_and(|max| self.yielded >= max) {
+            return None;
+        }
+        let output = self.next_inspected();
+        self.yielded += usize::from(output.is_some());
+        output
+    }
+
+    fn next_inspected(&mut self) -> Option<Output> {
+        loop {
+            if let Some(output) = self.next_walk_error() {
+                return Some(output);
+            }
+            let start = Instant::now();
+            let path = self.next_path();
+            self.record(|profile| profile.walk += start.elapsed());
+            let Some(path) = path else {
+                return self.next_walk_error();
+            };
+            if let Some(output) = self.open_and_inspect(&path) {
+                return Some(output);
             }
+        }
+    }
+
+    fn open_and_inspect(&self, path: &Path) -> Option<Output> {
+        if self.profile.is_some() {
+            return self.profiled_open_and_inspect(path);
+        }
+        match Repository::open(path) {
+            Ok(repo) => self.repo_ops(&repo),
+            Err(why) => self.open_error(path, why),
+        }
+    }
+
+    fn profiled_open_and_inspect(&self, path: &Path) -> Option<Output> {
+        let start = Instant::now();
+        let repo = Repository::open(path);
+        self.record(|profile| profile.walk += start.elapsed());
+        let repo = match repo {
+            Ok(repo) => repo,
+            Err(why) => return self.open_error(path, why),
+        };
+        let start = Instant::now();
+        let remote_before = REMOTE_TIME.get();
+        let output = self.repo_ops(&repo);
+        let remote = REMOTE_TIME.get() - remote_before;
+        self.record(|profile| profile.status += start.elapsed().saturating_sub(remote));
+        output
+    }
+
+    /// Most paths walked are not repos, and are skipped, but a repo that
+    /// can't be opened, like for lack of permissions, is reported
+    fn open_error(&self, path: &Path, why: Error) -> Option<Output> {
+        if why.code() == git2::ErrorCode::NotFound {
+            return None;
+        }
+        Some(Output {
+            path: if self.absolute_paths {
+                path.to_path_buf()
+            } else {
+                self.make_relative(path)
+            },
+            error: Some(why.into()),
+            ..Default::default()
+        })
+    }
+
+    fn path_matches(&self, path: &Path) -> bool {
+        let Some(ref matcher) = self.path_matcher else {
+            return true;
+        };
+        let path = if self.absolute_paths {
+            path.to_path_buf()
         } else {
-            target_dir.into()
+            self.make_relative(path)
+        };
+        // without the trailing slash of a workdir
+        matcher.is_match(path.components().collect::<PathBuf>())
+    }
+
+    fn wanted(&self, pending: &Set<PendingState>) -> bool {
+        self.only_states.is_empty() || self.only_states.iter().any(|state| pending.contains(state))
+    }
+
+    fn next_walk_error(&self) -> Option<Output> {
+        let (path, why) = self
+            .walk_errors
+            .as_ref()?
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .pop_front()?;
+        Some(Output {
+            path: if self.absolute_paths {
+                path
+            } else {
+                self.make_relative(&path)
+            },
+            error: Some(MrhError::Walk(why)),
+            ..Default::default()
+        })
+    }
+
+    fn make_relative(&self, target_dir: &Path) -> PathBuf {
+        let base = match (&self.relative_to, self.root_paths.as_slice()) {
+            (Some(base), _) | (None, [base]) => base,
+            _ => return target_dir.into(),
+        };
+        // repo paths are absolute, unlike a base such as "."
+        let relative = target_dir
+            .strip_prefix(base)
+            .ok()
+            .map(Path::to_path_buf)
+            .or_else(|| {
+                let base = base.canonicalize().ok()?;
+                Some(target_dir.strip_prefix(base).ok()?.to_path_buf())
+            });
+        match relative {
+            Some(path) if path.to_string_lossy().is_empty() => ".".into(),
+            Some(path) => path,
+            None => target_dir.into(),
         }
     }
 }
 
+/// Whether HEAD is any of the advertised refs, or an ancestor of one
+fn head_on_any_remote(
+    repo: &Repository,
+    local_head_oid: git2::Oid,
+    remote_list: &[git2::RemoteHead],
+) -> bool {
+    remote_list.iter().any(|head| {
+        head.oid() == local_head_oid
+            || repo
+                .graph_descendant_of(head.oid(), local_head_oid)
+                .unwrap_or(false)
+    })
+}
+
+/// The branch the remote's HEAD points at, falling back to "main",
+/// then "master", when that is unknown
+fn default_branch(repo: &Repository, remote_name: &str) -> Option<String> {
+    let prefix = format!("refs/remotes/{remote_name}/");
+    if let Ok(reference) = repo.find_reference(&format!("{prefix}HEAD")) {
+        if let Some(name) = reference
+            .symbolic_target()
+            .and_then(|target| target.strip_prefix(&prefix))
+        {
+            return Some(name.into());
+        }
+    }
+    ["main", "master"]
+        .into_iter()
+        .find(|name| repo.find_branch(name, git2::BranchType::Local).is_ok())
+        .map(String::from)
+}
+
+/// Whether HEAD's upstream used to be the remote's default branch,
+/// but the remote's HEAD now points elsewhere
+fn default_branch_renamed(
+    repo: &Repository,
+    remote: &str,
+    remote_list: &[git2::RemoteHead<'_>],
+) -> bool {
+    let find = |name: &str| remote_list.iter().find(|head| head.name() == name);
+    let Some(default) = find("HEAD").and_then(|head| head.symref_target()) else {
+        return false;
+    };
+    let Ok(head) = repo.head() else {
+        return false;
+    };
+    let Some(upstream) = head
+        .name()
+        .and_then(|name| repo.branch_upstream_name(name).ok())
+        .and_then(|name| name.as_str().map(String::from))
+    else {
+        return false;
+    };
+    let Some(branch) = upstream.strip_prefix(&format!("refs/remotes/{remote}/")) else {
+        return false;
+    };
+    if default.strip_prefix("refs/heads/") == Some(branch) {
+        return false;
+    }
+    // set on clone, so is what the default was back then
+    match repo.find_reference(&format!("refs/remotes/{remote}/HEAD")) {
+        Ok(reference) => reference.symbolic_target() == Some(upstream.as_str()),
+        // otherwise, guess from the usual default names, now gone
+        Err(_) => {
+            ["main", "master"].contains(&branch) && find(&format!("refs/heads/{branch}")).is_none()
+        }
+    }
+}
+
+/// Whether the local copy of the remote's default branch has both
+/// unpushed and unpulled commits, so can't simply be fast-forwarded
+fn default_branch_diverged(repo: &Repository, remote_list: &[git2::RemoteHead<'_>]) -> bool {
+    let find = |name: &str| remote_list.iter().find(|head| head.name() == name);
+    let Some(branch) = find("HEAD").and_then(|head| head.symref_target()) else {
+        return false;
+    };
+    let (Ok(local_oid), Some(remote_head)) = (repo.refname_to_id(branch), find(branch)) else {
+        return false;
+    };
+    // this fails if the remote commit is not fetched yet,
+    // which is reported as "unfetched commits" anyway
+    match repo.graph_ahead_behind(local_oid, remote_head.oid()) {
+        Ok((ahead, behind)) => ahead > 0 && behind > 0,
+        Err(_) => false,
+    }
+}
+
+/// Whether a submodule now points at another commit, as opposed to
+/// only having changes of its own, which also show up as modified
+fn gitlink_changed(delta: &git2::DiffDelta<'_>) -> bool {
+    delta.new_file().mode() == git2::FileMode::Commit
+        && delta.old_file().id() != delta.new_file().id()
+}
+
+/// Whether the working tree has no files, be they untracked or staged
+fn is_empty(repo: &Repository, opts: &mut StatusOptions) -> bool {
+    repo.statuses(Some(opts))
+        .is_ok_and(|statuses| statuses.is_empty())
+}
+
+/// Commits reachable from `head`, but not from `upstream`
+fn unpushed_commits(
+    repo: &Repository,
+    head: git2::Oid,
+    upstream: git2::Oid,
+) -> impl Iterator<Item = git2::Commit<'_>> {
+    let revwalk = repo.revwalk().ok().and_then(|mut revwalk| {
+        revwalk.push(head).ok()?;
+        revwalk.hide(upstream).ok()?;
+        Some(revwalk)
+    });
+    revwalk
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|oid| repo.find_commit(oid).ok())
+}
+
+fn submodule_ops(repo: &Repository, mut pending: Set<PendingState>) -> Set<PendingState> {
+    use git2::{SubmoduleIgnore, SubmoduleStatus};
+
+    let Ok(submodules) = repo.submodules() else {
+        return pending;
+    };
+    for submodule in submodules {
+        let Some(name) = submodule.name() else {
+            continue;
+        };
+        if let Ok(status) = repo.submodule_status(name, SubmoduleIgnore::None) {
+            if status.contains(SubmoduleStatus::WD_UNINITIALIZED) {
+                pending.insert(PendingState::UninitializedSubmodule);
+            } else if status
+                .intersects(SubmoduleStatus::WD_MODIFIED | SubmoduleStatus::INDEX_MODIFIED)
+            {
+                pending.insert(PendingState::ModifiedSubmodule);
+            }
+        }
+    }
+    pending
+}
+
+/// Globs that are invalid are left out
+fn glob_set<'a>(patterns: impl Iterator<Item = &'a str>) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for glob in patterns.flat_map(Glob::new) {
+        builder.add(glob);
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// Whether this is a directory whose name matches one of the `excluded` globs
+fn is_excluded(entry: &ignore::DirEntry, excluded: &GlobSet) -> bool {
+    // the root itself is never excluded
+    entry.depth() > 0
+        && entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_dir())
+        && excluded.is_match(entry.file_name())
+}
+
+/// Whether this is the git dir of a repo with a worktree, whatever its name,
+/// and not a dir that only happens to be named `.git`
+fn is_git_dir(entry: &ignore::DirEntry) -> bool {
+    let path = entry.path();
+    // cheap check first, since this runs for each dir walked
+    if !path.join("HEAD").is_file() {
+        return false;
+    }
+    let Ok(repo) = Repository::open(path) else {
+        return false;
+    };
+    // a bare repo has no worktree to be found at, so is reported as itself
+    if repo.is_bare() {
+        return false;
+    }
+    match (repo.path().canonicalize(), path.canonicalize()) {
+        (Ok(git_dir), Ok(path)) => git_dir == path,
+        _ => false,
+    }
+}
+
+/// Whether this is a directory whose parent is a repo's working tree
+fn is_inside_repo(entry: &ignore::DirEntry) -> bool {
+    if !entry
+        .file_type()
+        .is_some_and(|file_type| file_type.is_dir())
+    {
+        return false;
+    }
+    match entry.path().parent() {
+        Some(parent) => parent.join(".git").exists(),
+        None => false,
+    }
+}
+
+// credentials are only asked for by the callbacks set in `connect`
+fn remote_error(why: Error) -> MrhError {
+    if why.code() == git2::ErrorCode::Auth || why.class() == git2::ErrorClass::Callback {
+        MrhError::RemoteAuth(why.message().into())
+    } else {
+        why.into()
+    }
+}
+
+fn walk_error_path(why: &ignore::Error) -> Option<PathBuf> {
+    match why {
+        ignore::Error::WithPath { path, .. } => Some(path.clone()),
+        ignore::Error::Loop { child, .. } => Some(child.clone()),
+        ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+            walk_error_path(err)
+        }
+        _ => None,
+    }
+}
+
+/// Linked worktrees that live inside the repo at `path`,
+/// which the walk would otherwise skip, since it does not look inside repos
+fn nested_worktrees(path: &Path) -> Vec<PathBuf> {
+    // only a main worktree has a .git dir, and others can only be linked to it
+    if !path.join(".git").is_dir() {
+        return Vec::new();
+    }
+    let (Ok(repo), Ok(root)) = (Repository::open(path), path.canonicalize()) else {
+        return Vec::new();
+    };
+    let Ok(names) = repo.worktrees() else {
+        return Vec::new();
+    };
+    names
+        .iter()
+        .flatten()
+        .filter_map(|name| repo.find_worktree(name).ok())
+        .filter_map(|worktree| {
+            // keep the path in the same form as the walk, like relative
+            let nested = worktree.path().strip_prefix(&root).ok()?;
+            Some(path.join(nested))
+        })
+        .filter(|worktree| worktree.is_dir())
+        .collect()
+}
+
+fn head_age(repo: &Repository, head_oid: git2::Oid) -> Option<Duration> {
+    let commit = repo.find_commit(head_oid).ok()?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    // committed "in the future" (a skewed clock) counts as brand new
+    let age = (now - commit.time().seconds()).max(0);
+    Some(Duration::from_secs(age as u64))
+}
+
+fn has_recent_reset(repo: &Repository, window: Duration) -> bool {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+    let cutoff = now - window.as_secs() as i64;
+    if let Ok(reflog) = repo.reflog("HEAD") {
+        for entry in reflog.iter() {
+            if entry.committer().when().seconds() < cutoff {
+                continue;
+            }
+            if let Some(message) = entry.message() {
+                if message.starts_with("reset: moving to") {
+                    return true;
+                }
+            }
+        }
+    }
+    false
+}
+
+/// Non-comment lines of `.git/info/exclude`
+fn local_exclude_patterns(repo: &Repository) -> Vec<String> {
+    let Ok(contents) = fs::read_to_string(repo.path().join("info").join("exclude")) else {
+        return Vec::new();
+    };
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+fn operation_in_progress(repo: &Repository) -> Option<PendingState> {
+    use git2::RepositoryState::*;
+
+    match repo.state() {
+        Clean => None,
+        Merge => Some(PendingState::MergeInProgress),
+        Revert | RevertSequence => Some(PendingState::RevertInProgress),
+        CherryPick | CherryPickSequence => Some(PendingState::CherryPickInProgress),
+        Bisect => Some(PendingState::BisectInProgress),
+        Rebase | RebaseInteractive | RebaseMerge | ApplyMailboxOrRebase => {
+            Some(PendingState::RebaseInProgress)
+        }
+        ApplyMailbox => Some(PendingState::ApplyMailboxInProgress),
+    }
+}
+
+fn has_remote(repo: &Repository) -> bool {
+    repo.remotes().is_ok_and(|names| !names.is_empty())
+}
+
+fn has_missing_local_remote(repo: &Repository) -> bool {
+    let Some(workdir) = repo.workdir() else {
+        return false;
+    };
+    let Ok(names) = repo.remotes() else {
+        return false;
+    };
+    names
+        .iter()
+        .flatten()
+        .filter_map(|name| repo.find_remote(name).ok())
+        .filter_map(|remote| remote.url().and_then(|url| local_path(url, workdir)))
+        .any(|path| !path.exists())
+}
+
+/// Host part of the URL of remote `name`, or "localhost" for a local path
+fn remote_host(repo: &Repository, name: &str) -> Option<String> {
+    let remote = repo.find_remote(name).ok()?;
+    let url = remote.url()?;
+    // for bare repos, relative paths are relative to the repo itself
+    let base = repo.workdir().unwrap_or(repo.path());
+    if local_path(url, base).is_some() {
+        return Some("localhost".into());
+    }
+    let authority = match url.split_once("://") {
+        Some((_, rest)) => rest.split('/').next()?,
+        // scp-like syntax (e.g. git@github.com:user/repo)
+        None => url.split(':').next()?,
+    };
+    let host = authority.rsplit('@').next()?;
+    // leave out the port, minding the brackets of IPv6 addresses
+    let host = match host.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') => host,
+        _ => host,
+    };
+    Some(host.to_string())
+}
+
+/// Path of a remote URL that refers to the local filesystem
+fn local_path(url: &str, workdir: &Path) -> Option<PathBuf> {
+    if let Some(path) = url.strip_prefix("file://") {
+        return Some(path.into());
+    }
+    if url.contains("://") {
+        return None;
+    }
+    // scp-like syntax (e.g. git@github.com:user/repo),
+    // taking care not to mistake a Windows drive letter for a host
+    if let Some((host, _)) = url.split_once(':') {
+        if host.len() > 1 && !host.contains('/') {
+            return None;
+        }
+    }
+    // relative paths are relative to the working directory
+    Some(workdir.join(url))
+}
+
+fn head_author(repo: &Repository, head_oid: git2::Oid) -> Option<String> {
+    let commit = repo.find_commit(head_oid).ok()?;
+    let author = commit.author();
+    match (author.name(), author.email()) {
+        (Some(name), Some(email)) => Some(format!("{name} <{email}>")),
+        (Some(name), None) => Some(name.into()),
+        (None, Some(email)) => Some(format!("<{email}>")),
+        (None, None) => None,
+    }
+}
+
+/// Each tag's short name, along with its reference
+fn tag_refs(repo: &Repository) -> Vec<(String, git2::Reference<'_>)> {
+    let Ok(tags) = repo.tag_names(None) else {
+        return Vec::new();
+    };
+    tags.iter()
+        .flatten()
+        .filter_map(|tag| {
+            let reference = repo.find_reference(&format!("refs/tags/{tag}")).ok()?;
+            Some((tag.to_string(), reference))
+        })
+        .collect()
+}
+
+fn head_tag(repo: &Repository, head_oid: git2::Oid) -> Option<String> {
+    // peel, so that annotated tags are matched too
+    tag_refs(repo)
+        .into_iter()
+        .find(|(_, reference)| {
+            reference
+                .peel_to_commit()
+                .is_ok_and(|commit| commit.id() == head_oid)
+        })
+        .map(|(tag, _)| tag)
+}
+
+/// The subset of `core.whitespace` that [`Crawler::flag_whitespace`] checks
+struct WhitespaceRules {
+    blank_at_eol: bool,
+    space_before_tab: bool,
+    tab_in_indent: bool,
+    cr_at_eol: bool,
+}
+
+impl WhitespaceRules {
+    fn from_config(repo: &Repository) -> Self {
+        // git's defaults
+        let mut rules = Self {
+            blank_at_eol: true,
+            space_before_tab: true,
+            tab_in_indent: false,
+            cr_at_eol: false,
+        };
+        let setting = repo
+            .config()
+            .and_then(|config| config.get_string("core.whitespace"))
+            .unwrap_or_default();
+        for rule in setting.split(',').map(str::trim) {
+            let (enabled, name) = match rule.strip_prefix('-') {
+                Some(name) => (false, name),
+                None => (true, rule),
+            };
+            match name {
+                "blank-at-eol" | "trailing-space" => rules.blank_at_eol = enabled,
+                "space-before-tab" => rules.space_before_tab = enabled,
+                "tab-in-indent" => rules.tab_in_indent = enabled,
+                "cr-at-eol" => rules.cr_at_eol = enabled,
+                _ => (),
+            }
+        }
+        rules
+    }
+
+    fn violated_by(&self, line: &[u8]) -> bool {
+        let mut line = line.strip_suffix(b"\n").unwrap_or(line);
+        if self.cr_at_eol {
+            line = line.strip_suffix(b"\r").unwrap_or(line);
+        }
+        let indent_len = line
+            .iter()
+            .take_while(|byte| matches!(byte, b' ' | b'\t'))
+            .count();
+        let indent = &line[..indent_len];
+        (self.blank_at_eol && line.last().is_some_and(u8::is_ascii_whitespace))
+            || (self.space_before_tab && indent.windows(2).any(|pair| pair == b" \t"))
+            || (self.tab_in_indent && indent.contains(&b'\t'))
+    }
+}
+
+/// Whether lines added by staged changes have whitespace errors
+fn has_whitespace_errors(repo: &Repository) -> bool {
+    let rules = WhitespaceRules::from_config(repo);
+    // an unborn HEAD means everything staged is new
+    let head_tree = repo.head().and_then(|head| head.peel_to_tree()).ok();
+    let Ok(diff) = repo.diff_tree_to_index(head_tree.as_ref(), None, None) else {
+        return false;
+    };
+    let mut found = false;
+    // stopping early (by returning false) makes this return an error
+    let _ = diff.foreach(
+        &mut |_, _| true,
+        None,
+        None,
+        Some(&mut |_, _, line| {
+            found = line.origin() == '+' && rules.violated_by(line.content());
+            !found
+        }),
+    );
+    found
+}
+
+/// Local branches (just HEAD's, unless `all`) with an upstream configured,
+/// but whose remote-tracking branch is missing
+fn gone_upstream_branches(repo: &Repository, all: bool) -> Vec<String> {
+    let Ok(branches) = repo.branches(Some(git2::BranchType::Local)) else {
+        return Vec::new();
+    };
+    branches
+        .flatten()
+        .filter(|(branch, _)| all || branch.is_head())
+        .filter_map(|(branch, _)| {
+            let refname = branch.get().name()?;
+            // this only reads config, so works even if the ref is gone
+            let upstream = repo.branch_upstream_name(refname).ok()?;
+            let gone = repo.find_reference(upstream.as_str()?).is_err();
+            let name = branch.name().ok()??;
+            gone.then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Local branches, other than `current`, that are ahead of their upstream
+fn unpushed_other_branches(repo: &Repository, current: Option<&str>) -> Vec<String> {
+    let Ok(branches) = repo.branches(Some(git2::BranchType::Local)) else {
+        return Vec::new();
+    };
+    branches
+        .flatten()
+        .filter_map(|(branch, _)| {
+            let name = branch.name().ok()??;
+            if Some(name) == current {
+                return None; // already covered by "unpushed commits"
+            }
+            let local = branch.get().target()?;
+            let upstream = branch.upstream().ok()?.get().target()?;
+            let (ahead, _) = repo.graph_ahead_behind(local, upstream).ok()?;
+            (ahead > 0).then(|| name.to_string())
+        })
+        .collect()
+}
+
+/// Whether any of `remote`'s remote-tracking branches are missing from
+/// `remote_heads`, assuming the default fetch refspec
+fn needs_prune(repo: &Repository, remote: &str, remote_heads: &Set<&str>) -> bool {
+    let prefix = format!("refs/remotes/{remote}/");
+    let Ok(references) = repo.references_glob(&format!("{prefix}*")) else {
+        return false;
+    };
+    references
+        .flatten()
+        // refs/remotes/<remote>/HEAD mirrors the remote's HEAD, not a branch
+        .filter(|reference| reference.kind() == Some(git2::ReferenceType::Direct))
+        .filter_map(|reference| {
+            let branch = reference.name()?.strip_prefix(&prefix)?.to_string();
+            Some(format!("refs/heads/{branch}"))
+        })
+        .any(|head| !remote_heads.contains(head.as_str()))
+}
+
+fn has_dangling_tags(repo: &Repository) -> bool {
+    let Ok(branches) = repo.branches(None) else {
+        return false;
+    };
+    let tips: Vec<_> = branches
+        .flatten()
+        .filter_map(|(branch, _)| branch.get().target())
+        .collect();
+    tag_refs(repo).into_iter().any(|(_, reference)| {
+        // tags on trees or blobs are not about commits at all
+        let Ok(commit) = reference.peel_to_commit() else {
+            return false;
+        };
+        let oid = commit.id();
+        !tips
+            .iter()
+            .any(|&tip| tip == oid || repo.graph_descendant_of(tip, oid).unwrap_or(false))
+    })
+}
+
+fn ahead_behind_ref(repo: &Repository, head: git2::Oid, refname: &str) -> Option<(usize, usize)> {
+    let target = repo.revparse_single(refname).ok()?.peel_to_commit().ok()?;
+    repo.graph_ahead_behind(head, target.id()).ok()
+}
+
+fn upstream_remote_name(repo: &Repository, branch: &Branch<'_>) -> Option<String> {
+    let refname = branch.get().name()?;
+    let remote = repo.branch_upstream_remote(refname).ok()?;
+    // "." is what git records when the upstream is a local branch
+    remote
+        .as_str()
+        .filter(|name| *name != ".")
+        .map(String::from)
+}
+
 impl Iterator for Crawler {
     type Item = Output;
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            match self.iter.next() {
-                None => return None,
-                Some(repo) => {
-                    if let Some(output) = self.repo_ops(&repo) {
-                        return Some(output);
-                    }
-                }
-            }
+        if !self.sorted {
+            return self.next_output();
+        }
+        if self.sorted_outputs.is_none() {
+            let mut outputs: Vec<_> = std::iter::from_fn(|| self.next_output()).collect();
+            outputs.sort_by(|a, b| a.path.cmp(&b.path));
+            self.sorted_outputs = Some(outputs.into_iter());
         }
+        self.sorted_outputs.as_mut()?.next()
     }
 }
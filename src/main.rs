@@ -1,18 +1,25 @@
 #[cfg(feature = "json")]
+use serde::Deserialize;
+#[cfg(feature = "toml")]
 use serde::Serialize;
 
 use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
     fmt::Write as _,
-    io::Write,
+    fs,
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
     process,
+    time::Duration,
 };
 
 use ansi_term::Color;
 use anyhow::{bail, ensure, Result};
 use clap::Parser;
+use indexmap::IndexMap;
 
-use mrh::Crawler;
+use mrh::{Crawler, PendingState, Summary};
 
 const CYAN: Color = Color::Fixed(6);
 const BRIGHT_BLACK: Color = Color::Fixed(8);
@@ -24,94 +31,1013 @@ struct Cli {
     /// Only show repos with pending action
     #[arg(long)]
     pending: bool,
+    /// Only show repos in this pending state, like outdated-branch
+    /// (can be repeated)
+    #[arg(long, value_name = "STATE", value_parser = clap::value_parser!(PendingState))]
+    only: Vec<PendingState>,
+    /// Never show this pending state, like outdated-branch
+    /// (can be repeated)
+    #[arg(long, value_name = "STATE", value_parser = clap::value_parser!(PendingState))]
+    ignore: Vec<PendingState>,
     /// Do not include untracked files in output
     #[arg(long)]
     ignore_untracked: bool,
     /// Do not include repos that have no commits
     #[arg(long)]
     ignore_uncommitted_repos: bool,
+    /// Check for repos with neither commits nor files
+    #[arg(long)]
+    flag_empty: bool,
+    /// Check if the working tree has ignored files
+    #[arg(long)]
+    include_ignored: bool,
+    /// Check if a merge, rebase, or similar is in progress
+    #[arg(long)]
+    flag_state: bool,
+    /// Do not look for repos deeper than this below the root path
+    #[arg(long, value_name = "DEPTH")]
+    max_depth: Option<usize>,
+    /// Stop after showing this many repos
+    #[arg(long, value_name = "N")]
+    limit: Option<usize>,
+    /// Also look for repos inside other repos' working trees
+    #[arg(long)]
+    recurse_submodule_like: bool,
+    /// Also look for repos through symlinked directories
+    #[arg(long)]
+    follow_links: bool,
+    /// Also look for repos inside directories whose names start with a dot,
+    /// like .config, which are skipped by default
+    #[arg(long)]
+    include_hidden: bool,
+    /// Report directories that could not be walked, like unreadable ones,
+    /// instead of skipping them silently
+    #[arg(long)]
+    report_walk_errors: bool,
+    /// Also inspect bare repos, like mirrors
+    #[arg(long)]
+    include_bare: bool,
+    /// Show repos in order of their path, but only once all are inspected
+    #[arg(long)]
+    sorted: bool,
+    /// Skip directories whose name matches this glob (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+    /// Only show repos whose displayed path matches this glob (repeatable)
+    #[arg(long = "match", value_name = "GLOB")]
+    path_glob: Vec<String>,
+    /// Only show repos that have this branch checked out
+    #[arg(long, value_name = "NAME")]
+    on_branch: Option<String>,
+    /// Remember where repos were found, to speed up the next crawl
+    #[arg(long, value_name = "FILE")]
+    path_cache: Option<PathBuf>,
     /// Display absolute paths for repos
     #[arg(long)]
     absolute_paths: bool,
     /// Check if HEAD is untagged
     #[arg(long)]
     untagged_heads: bool,
+    /// Check for tags on commits that no branch contains
+    #[arg(long)]
+    flag_dangling_tags: bool,
+    /// Check if the repo is a shallow clone
+    #[arg(long)]
+    flag_shallow: bool,
+    /// Check if HEAD is detached
+    #[arg(long)]
+    detached_head: bool,
+    /// Check if HEAD is not on the remote's default branch
+    #[arg(long)]
+    off_default_branch: bool,
+    /// Show the tag at HEAD, if there is one
+    #[arg(long)]
+    show_tag: bool,
+    /// Show the author of the HEAD commit
+    #[arg(long)]
+    show_author: bool,
+    /// Check if there are stashed changes
+    #[arg(long)]
+    include_stashed: bool,
+    /// Check if staged changes add lines with whitespace errors,
+    /// as configured by core.whitespace
+    #[arg(long)]
+    flag_whitespace: bool,
+    /// Check if unpushed commits look like work in progress
+    #[arg(long)]
+    flag_wip: bool,
+    /// Commit summary prefix that marks work in progress (repeatable),
+    /// replacing the built-in list
+    #[arg(long, value_name = "PREFIX", requires = "flag_wip")]
+    wip_pattern: Vec<String>,
+    /// Check if unpushed commits are not signed
+    #[arg(long)]
+    require_signed: bool,
+    /// Check if `.git/info/exclude` has patterns, which are not shared
+    #[arg(long)]
+    local_excludes: bool,
+    /// Check other local branches for unpushed commits too
+    #[arg(long)]
+    check_all_branches: bool,
+    /// Check if submodules are modified or uninitialized
+    #[arg(long)]
+    include_submodules: bool,
+    /// Check if HEAD was reset within the given number of days
+    #[arg(long, value_name = "DAYS")]
+    recent_resets: Option<u64>,
+    /// Check if the HEAD commit is older than the given number of days
+    #[arg(long, value_name = "DAYS")]
+    stale_after: Option<u64>,
+    /// Check if remotes that are local paths still exist
+    #[arg(long)]
+    local_remotes: bool,
+    /// Check if there is no remote configured
+    #[arg(long)]
+    flag_no_remote: bool,
+    /// Check if the branch has no upstream, despite there being a remote
+    #[arg(long)]
+    flag_no_upstream: bool,
+    /// Check if the branch tracks a remote branch that no longer exists
+    /// (all local branches, with --check-all-branches)
+    #[arg(long)]
+    flag_gone_upstream: bool,
+    /// Check for untracked or added files that look like secrets
+    #[arg(long)]
+    warn_secrets: bool,
+    /// File name glob considered a possible secret (repeatable),
+    /// replacing the built-in list
+    #[arg(long, value_name = "GLOB", requires = "warn_secrets")]
+    secret_pattern: Vec<String>,
+    /// Report unpushed commits and outdated branches against this ref,
+    /// like origin/main, instead of the upstream branch
+    #[arg(long, value_name = "REF")]
+    compare_against: Option<String>,
+    /// Report "diverged" in place of both unpushed commits and outdated branch
+    #[arg(long)]
+    collapse_diverged: bool,
+    /// Report "uncommitted changes" in place of both staged and unstaged changes
+    #[arg(long)]
+    combine_changes: bool,
+    /// Show how far HEAD is ahead of (or behind) this ref, like a tag
+    #[arg(long, value_name = "REF")]
+    since_ref: Option<String>,
     /// Compare against remote repo, most likely over the network
     #[arg(long, value_parser = ["ssh-key", "ssh-agent"])]
     ssh_auth_method: Option<String>,
+    /// Name of the remote to compare against
+    #[arg(long, value_name = "NAME", default_value = "origin")]
+    remote_name: String,
+    /// Private key to use with --ssh-auth-method=ssh-key,
+    /// instead of the first found in ~/.ssh
+    ///
+    /// If the key is encrypted, its passphrase is taken from the
+    /// MRH_SSH_PASSPHRASE environment variable.
+    #[arg(long, value_name = "PATH", requires = "ssh_auth_method")]
+    ssh_key: Option<PathBuf>,
+    /// Give up on a repo's remote after this many seconds
+    #[arg(long, value_name = "SECS", requires = "ssh_auth_method")]
+    remote_timeout: Option<u64>,
+    /// Reuse what remotes had to say for this many seconds,
+    /// for repos whose HEAD has not moved since (needs JSON support)
+    #[arg(long, value_name = "SECS", requires = "ssh_auth_method")]
+    cache_ttl: Option<u64>,
+    /// Do not use the remote cache, even if --cache-ttl is given,
+    /// like in a config file
+    #[arg(long)]
+    no_cache: bool,
+    /// Check if HEAD is on none of the remotes
+    #[arg(long, requires = "ssh_auth_method")]
+    all_remotes: bool,
+    /// Fetch from the remote first, updating remote-tracking branches
+    #[arg(long, requires = "ssh_auth_method")]
+    fetch: bool,
+    /// Check if remote-tracking branches are gone from the remote,
+    /// so need pruning
+    #[arg(long, requires = "ssh_auth_method")]
+    flag_prune: bool,
+    /// Check if still tracking the remote's old default branch,
+    /// like after a rename from master to main
+    #[arg(long, requires = "ssh_auth_method")]
+    flag_default_renamed: bool,
     /// Display output in JSON format
     #[arg(long)]
     output_json: bool,
-    /// Choose a path where to start the crawl
+    /// Display output as one JSON object per line, each flushed
+    /// as soon as its repo is inspected (so one at a time, ignoring --jobs)
+    #[arg(long, conflicts_with = "output_json")]
+    output_ndjson: bool,
+    /// Display output in YAML format, once all repos are inspected
+    #[arg(long, conflicts_with_all = ["output_json", "output_ndjson"])]
+    output_yaml: bool,
+    /// Display output in TOML format, once all repos are inspected
+    #[arg(long, conflicts_with_all = ["output_json", "output_ndjson", "output_yaml"])]
+    output_toml: bool,
+    /// Group repos under their parent directory, with a count of clean ones
+    #[arg(long, conflicts_with_all = ["output_json", "output_ndjson", "output_yaml", "output_toml"])]
+    group_by_dir: bool,
+    /// File of `state = label` lines, to display states in other words
+    #[arg(long, value_name = "FILE", value_parser = read_labels)]
+    labels: Option<Labels>,
+    /// Show each repo as a multi-line block, with one pending action per line,
+    /// and more details, like how far ahead or behind upstream
+    #[arg(long, visible_alias = "verbose", conflicts_with_all = ["output_json", "output_ndjson", "output_yaml", "output_toml", "group_by_dir"])]
+    verbose_blocks: bool,
+    /// Finish with a count of repos, and of those pending or errored
+    #[arg(long)]
+    summary: bool,
+    /// Finish with a count of repos per remote host
+    #[arg(long, conflicts_with_all = ["output_json", "output_ndjson", "output_yaml", "output_toml", "statusline"])]
+    host_summary: bool,
+    /// Print a single line with counts of pending and errored repos
+    #[arg(long, conflicts_with_all = ["output_json", "output_ndjson", "output_yaml", "output_toml", "group_by_dir", "verbose_blocks"])]
+    statusline: bool,
+    /// Glyph that precedes the count of pending repos in --statusline
+    #[arg(long, value_name = "GLYPH", default_value = "⚑")]
+    pending_glyph: String,
+    /// Glyph that precedes the count of errored repos in --statusline
+    #[arg(long, value_name = "GLYPH", default_value = "✗")]
+    error_glyph: String,
+    /// File recording which repos had pending action on the previous run
+    #[arg(long, value_name = "FILE")]
+    state_file: Option<PathBuf>,
+    /// Only show repos whose pending action is new since the previous run
+    #[arg(long, requires = "state_file")]
+    only_new_problems: bool,
+    /// Exit with status 1 if any repo has one of these pending states
+    /// (or "error"); any state counts if none are listed
+    #[arg(
+        long,
+        value_name = "CATEGORY",
+        num_args = 0..,
+        value_delimiter = ',',
+        require_equals = true,
+        value_parser = parse_exit_category
+    )]
+    exit_on: Option<Vec<ExitCategory>>,
+    /// Exit with status 1 if any repo has pending action,
+    /// or with status 2 if any repo could not be inspected
+    #[arg(long)]
+    exit_code: bool,
+    /// Print nothing, exiting with status 1 as soon as a repo
+    /// with pending action is found
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output_json",
+            "output_ndjson",
+            "output_yaml",
+            "output_toml",
+            "group_by_dir",
+            "verbose_blocks",
+            "host_summary",
+            "summary",
+            "statusline",
+            "exit_code",
+            "exit_on",
+        ]
+    )]
+    quiet: bool,
+    /// Crawl a remote machine instead, using the mrh found there
+    ///
+    /// That mrh needs to have been built with JSON support.
+    #[arg(long, value_name = "USER@HOST:PATH")]
+    ssh_host: Option<String>,
+    /// Show a full-screen view that refreshes in place (press q to quit)
+    #[arg(
+        long,
+        conflicts_with_all = [
+            "output_json",
+            "output_ndjson",
+            "output_yaml",
+            "output_toml",
+            "group_by_dir",
+            "verbose_blocks",
+            "host_summary",
+            "summary",
+            "progress",
+            "statusline",
+            "ssh_host",
+            "quiet",
+        ]
+    )]
+    dashboard: bool,
+    /// Seconds between refreshes of the --dashboard view
+    #[arg(long, value_name = "SECS", default_value_t = 10)]
+    refresh: u64,
+    /// Print time spent in each phase of the crawl, to stderr
+    #[arg(long)]
+    profile: bool,
+    /// Print each repo as it is about to be inspected, to stderr
+    #[arg(long)]
+    progress: bool,
+    /// Inspect up to this many repos (and talk to as many remotes) at once,
+    /// or 0 for one per CPU; with more than 1, repos are shown all at
+    /// the end, instead of as each is inspected
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+    /// When to color the output: auto (if NO_COLOR is unset and
+    /// output goes to a terminal), always, or never
+    #[arg(
+        long,
+        value_name = "WHEN",
+        default_value = "auto",
+        value_parser = ["auto", "always", "never"]
+    )]
+    color: String,
+    #[arg(skip)]
+    colored: bool,
+    /// Inspect the repos listed in this file, one path per line,
+    /// instead of crawling for them
+    #[arg(long, value_name = "MANIFEST", conflicts_with_all = ["root_paths", "ssh_host"])]
+    from_file: Option<PathBuf>,
+    #[arg(skip)]
+    manifest_paths: Option<Vec<PathBuf>>,
+    /// Choose paths where to start the crawl
     #[arg(default_value = ".")]
-    root_path: PathBuf,
+    root_paths: Vec<PathBuf>,
 }
 
+/// What `mrh::Output` is serialized as, read back from `--ssh-host`
 #[cfg(feature = "json")]
-#[derive(Serialize)]
+#[derive(Deserialize)]
 struct Output {
     pub path: String,
+    pub branch: Option<String>,
     pub pending: Option<Vec<String>>,
     pub error: Option<String>,
+    pub remote: Option<String>,
+    pub remote_host: Option<String>,
+    pub ahead: Option<usize>,
+    pub behind: Option<usize>,
+    pub secrets: Option<Vec<String>>,
+    pub since_ref: Option<SinceRef>,
+    pub tag: Option<String>,
+    pub last_author: Option<String>,
+    pub local_excludes: Option<Vec<String>>,
+    pub head_age_secs: Option<u64>,
+    pub untracked_count: Option<usize>,
+    pub unpushed_branches: Option<Vec<String>>,
+    pub gone_upstreams: Option<Vec<String>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(Deserialize)]
+struct SinceRef {
+    pub ahead: usize,
+    pub behind: usize,
 }
 
 fn main() -> Result<()> {
-    let cli = Cli::parse();
-    ensure!(
-        cli.root_path.metadata()?.is_dir(),
-        "root path should be a directory",
-    );
-    let crawler = Crawler::new(&cli.root_path)
+    #[cfg(windows)]
+    ansi_term::enable_ansi_support().unwrap();
+    let mut cli = parse_cli()?;
+    cli.colored = match cli.color.as_str() {
+        "always" => true,
+        "never" => false,
+        // see https://no-color.org
+        _ => {
+            std::env::var_os("NO_COLOR").map_or(true, |value| value.is_empty())
+                && io::stdout().is_terminal()
+        }
+    };
+    #[cfg(not(feature = "json"))]
+    if cli.cache_ttl.is_some() && !cli.no_cache {
+        bail!("Support for the remote cache (kept as JSON) not compiled in");
+    }
+    if let Some(ref file) = cli.from_file {
+        cli.manifest_paths = Some(read_manifest(file)?);
+    }
+    let ssh_outputs = match cli.ssh_host {
+        Some(ref target) => Some(crawl_over_ssh(target, &cli)?),
+        None => {
+            for root_path in &cli.root_paths {
+                ensure!(
+                    root_path.metadata()?.is_dir(),
+                    "root path should be a directory",
+                );
+            }
+            None
+        }
+    };
+    if cli.dashboard {
+        return run_dashboard(&cli);
+    }
+    if cli.quiet {
+        let pending = match ssh_outputs {
+            Some(ref outputs) => outputs.iter().any(|output| output.pending.is_some()),
+            None => crawler(&cli).has_pending(),
+        };
+        process::exit(i32::from(pending));
+    }
+    let mut crawler = crawler(&cli);
+    let baseline = match cli.state_file {
+        Some(ref file) if cli.only_new_problems => Some(read_state(file)?),
+        _ => None,
+    };
+    let mut problems = Vec::new();
+    let outputs: Box<dyn Iterator<Item = mrh::Output>> = match ssh_outputs {
+        Some(outputs) => Box::new(outputs.into_iter()),
+        None if jobs(&cli) == 1 => Box::new(&mut crawler),
+        None => Box::new(crawler.collect_parallel().into_iter()),
+    };
+    let mut failed = false;
+    let mut exit_code = 0;
+    let mut hosts: IndexMap<String, usize> = IndexMap::new();
+    let mut summary = Summary::default();
+    let outputs = outputs
+        .filter(|output| {
+            if output.pending.is_none() {
+                return baseline.is_none();
+            }
+            problems.push(output.path.clone());
+            baseline
+                .as_ref()
+                .map_or(true, |baseline| !baseline.contains(&output.path))
+        })
+        .inspect(|output| {
+            if let Some(ref categories) = cli.exit_on {
+                failed |= should_fail(output, categories);
+            }
+            if cli.exit_code {
+                if output.error.is_some() {
+                    exit_code = 2;
+                } else if output.pending.is_some() {
+                    exit_code = exit_code.max(1);
+                }
+            }
+            summary.record(output);
+            if cli.host_summary {
+                let host = output.remote_host.as_deref().unwrap_or("(none)");
+                *hosts.entry(host.into()).or_default() += 1;
+            }
+        });
+    if cli.statusline {
+        display_statusline(outputs, &cli.pending_glyph, &cli.error_glyph);
+    } else if cli.group_by_dir {
+        display_grouped(outputs, &cli)?;
+    } else if cli.output_yaml {
+        display_yaml(outputs.collect())?;
+    } else if cli.output_toml {
+        display_toml(outputs.collect())?;
+    } else {
+        for output in outputs {
+            if cli.output_json || cli.output_ndjson {
+                display_json(output, cli.output_ndjson);
+            } else if cli.verbose_blocks {
+                print_line(&format_block(output, &cli)?);
+            } else {
+                display_human(output, &cli)?;
+            }
+        }
+    }
+    if cli.host_summary {
+        hosts.sort_by(|_, a, _, b| b.cmp(a));
+        let hosts: Vec<_> = hosts
+            .iter()
+            .map(|(host, count)| format!("{host}: {count}"))
+            .collect();
+        print_line(&hosts.join(", "));
+    }
+    if cli.summary {
+        display_summary(&summary, cli.output_json || cli.output_ndjson)?;
+    }
+    if let Some(file) = cli.state_file {
+        write_state(&file, &problems)?;
+    }
+    if let Some(profile) = crawler.profile_report() {
+        eprintln!(
+            "walk: {:.2?}, status: {:.2?}, remote: {:.2?}",
+            profile.walk, profile.status, profile.remote,
+        );
+    }
+    if failed {
+        exit_code = exit_code.max(1);
+    }
+    if exit_code != 0 {
+        // exiting skips dropping the crawler, where the cache gets saved
+        #[cfg(feature = "json")]
+        crawler.flush_cache();
+        process::exit(exit_code);
+    }
+    Ok(())
+}
+
+/// What `--exit-on` looks out for
+#[derive(Clone, Copy, PartialEq)]
+enum ExitCategory {
+    State(PendingState),
+    Error,
+}
+
+fn parse_exit_category(category: &str) -> Result<ExitCategory> {
+    if category.trim() == "error" {
+        return Ok(ExitCategory::Error);
+    }
+    Ok(ExitCategory::State(category.parse()?))
+}
+
+fn should_fail(output: &mrh::Output, categories: &[ExitCategory]) -> bool {
+    if categories.is_empty() {
+        return output.pending.is_some() || output.error.is_some();
+    }
+    if output.error.is_some() && categories.contains(&ExitCategory::Error) {
+        return true;
+    }
+    match output.pending {
+        Some(ref pending) => pending
+            .iter()
+            .any(|state| categories.contains(&ExitCategory::State(*state))),
+        None => false,
+    }
+}
+
+fn crawler(cli: &Cli) -> Crawler {
+    let mut crawler = match cli.manifest_paths {
+        Some(ref paths) => Crawler::from_paths(paths.clone()),
+        None => Crawler::new_multi(cli.root_paths.clone()),
+    };
+    if let Some(depth) = cli.max_depth {
+        crawler = crawler.max_depth(depth);
+    }
+    if let Some(max) = cli.limit {
+        crawler = crawler.limit(max);
+    }
+    for pattern in &cli.path_glob {
+        crawler = crawler.path_glob(pattern.clone());
+    }
+    // paths are otherwise left as is, since there's no single root
+    if cli.manifest_paths.is_some() || cli.root_paths.len() > 1 {
+        if let Ok(dir) = std::env::current_dir() {
+            crawler = crawler.relative_to(dir);
+        }
+    }
+    if let Some(ref file) = cli.path_cache {
+        crawler = crawler.path_cache(file.clone());
+    }
+    if let Some(ref name) = cli.on_branch {
+        crawler = crawler.on_branch(name.clone());
+    }
+    if let Some(ref refname) = cli.compare_against {
+        crawler = crawler.compare_against(refname.clone());
+    }
+    // not a flag, which would leave the passphrase in shell history
+    if let Ok(passphrase) = std::env::var("MRH_SSH_PASSPHRASE") {
+        crawler = crawler.ssh_passphrase(passphrase);
+    }
+    if let Ok(token) = std::env::var("MRH_HTTPS_TOKEN") {
+        crawler = crawler.https_token(token);
+    }
+    if let Ok(username) = std::env::var("MRH_HTTPS_USERNAME") {
+        crawler = crawler.https_username(username);
+    }
+    if cli.progress {
+        crawler = crawler.on_progress(Box::new(|path| eprintln!("checking {}", path.display())));
+    }
+    if !cli.wip_pattern.is_empty() {
+        crawler = crawler.wip_patterns(cli.wip_pattern.clone());
+    }
+    if let Some(ref path) = cli.ssh_key {
+        crawler = crawler.ssh_key_path(path.clone());
+    }
+    if let Some(secs) = cli.remote_timeout {
+        crawler = crawler.remote_timeout(Duration::from_secs(secs));
+    }
+    if let Some(days) = cli.recent_resets {
+        crawler = crawler.recent_resets(Duration::from_secs(days * 24 * 60 * 60));
+    }
+    if let Some(days) = cli.stale_after {
+        crawler = crawler.stale_after(Duration::from_secs(days * 24 * 60 * 60));
+    }
+    if !cli.secret_pattern.is_empty() {
+        crawler = crawler.secret_patterns(cli.secret_pattern.clone());
+    }
+    crawler = crawler
+        .recurse_submodule_like(cli.recurse_submodule_like)
+        .follow_links(cli.follow_links)
+        .skip_hidden(!cli.include_hidden)
+        .report_walk_errors(cli.report_walk_errors)
+        .include_bare(cli.include_bare)
+        .sorted(cli.sorted)
+        .exclude_dirs(cli.exclude.clone())
         .pending(cli.pending)
+        .only_states(cli.only.clone())
+        .ignore_states(cli.ignore.clone())
         .ignore_untracked(cli.ignore_untracked)
         .ignore_uncommitted_repos(cli.ignore_uncommitted_repos)
-        .access_remote(cli.ssh_auth_method)
+        .flag_empty(cli.flag_empty)
+        .include_ignored(cli.include_ignored)
+        .flag_repo_state(cli.flag_state)
+        .access_remote(cli.ssh_auth_method.clone())
+        .remote_name(cli.remote_name.clone())
+        .all_remotes(cli.all_remotes)
+        .do_fetch(cli.fetch)
+        .flag_prune(cli.flag_prune)
+        .flag_default_renamed(cli.flag_default_renamed)
         .absolute_paths(cli.absolute_paths)
-        .untagged_heads(cli.untagged_heads);
-    for output in crawler {
-        if cli.output_json {
-            display_json(output);
-        } else {
-            display_human(output)?;
+        .untagged_heads(cli.untagged_heads)
+        .flag_dangling_tags(cli.flag_dangling_tags)
+        .flag_shallow(cli.flag_shallow)
+        .detached_head(cli.detached_head)
+        .off_default_branch(cli.off_default_branch)
+        .show_tag(cli.show_tag)
+        .show_author(cli.show_author)
+        .include_stashed(cli.include_stashed)
+        .flag_whitespace(cli.flag_whitespace)
+        .local_excludes(cli.local_excludes)
+        .check_all_branches(cli.check_all_branches)
+        .include_submodules(cli.include_submodules)
+        .local_remotes(cli.local_remotes)
+        .flag_no_remote(cli.flag_no_remote)
+        .flag_no_upstream(cli.flag_no_upstream)
+        .flag_gone_upstream(cli.flag_gone_upstream)
+        .warn_secrets(cli.warn_secrets)
+        .flag_wip(cli.flag_wip)
+        .require_signed_commits(cli.require_signed)
+        .collapse_diverged(cli.collapse_diverged)
+        .combine_changes(cli.combine_changes)
+        .since_ref(cli.since_ref.clone())
+        .profile(cli.profile)
+        .parallel(jobs(cli))
+        .remote_concurrency(jobs(cli));
+    #[cfg(feature = "json")]
+    if let (Some(secs), false) = (cli.cache_ttl, cli.no_cache) {
+        if let Some(dir) = dirs_next::config_dir().map(|dir| dir.join("mrh")) {
+            // a cache that can't be written is simply not used next time
+            let _ = fs::create_dir_all(&dir);
+            let file = dir.join("remote-cache.json");
+            crawler = crawler.remote_cache(file, Duration::from_secs(secs));
         }
     }
+    crawler
+}
+
+fn display_human(result: mrh::Output, cli: &Cli) -> Result<()> {
+    let output = format_human(result, cli)?;
+    print_line(&output);
     Ok(())
 }
 
-fn display_human(result: mrh::Output) -> Result<()> {
-    #[cfg(windows)]
-    ansi_term::enable_ansi_support().unwrap();
-    let current_dir = match std::env::current_dir() {
-        Ok(dir) => dir,
-        Err(why) => {
-            bail!(
-                "{}: Could not read current directory: {}",
-                BRIGHT_RED.paint("error"),
-                why,
-            );
+fn read_state(file: &Path) -> Result<HashSet<PathBuf>> {
+    match fs::read_to_string(file) {
+        Ok(contents) => Ok(contents.lines().map(PathBuf::from).collect()),
+        // first run, so there's no baseline yet
+        Err(why) if why.kind() == io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(why) => bail!("could not read state file {}: {why}", file.display()),
+    }
+}
+
+fn parse_cli() -> Result<Cli> {
+    #[cfg_attr(not(feature = "config"), allow(unused_mut))]
+    let mut args: Vec<OsString> = std::env::args_os().collect();
+    #[cfg(feature = "config")]
+    if let Some(file) = config_file() {
+        let defaults = read_config(&file)?;
+        args = merge_config(args, defaults);
+    }
+    Ok(Cli::parse_from(args))
+}
+
+/// Add the config file flags to the command line ones, except for those
+/// the command line gives (or conflicts with), so its values replace
+/// the config ones, instead of adding to them
+#[cfg(feature = "config")]
+fn merge_config(mut args: Vec<OsString>, defaults: Vec<(String, Vec<OsString>)>) -> Vec<OsString> {
+    use clap::{parser::ValueSource, CommandFactory};
+
+    let command = Cli::command();
+    // only to see what is given, errors are left for the real parse
+    let Ok(matches) = command
+        .clone()
+        .ignore_errors(true)
+        .try_get_matches_from(&args)
+    else {
+        return args;
+    };
+    let given: Vec<_> = command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .collect();
+    let conflicts = |a: &clap::Arg, b: &clap::Arg| {
+        command
+            .get_arg_conflicts_with(a)
+            .iter()
+            .any(|arg| arg.get_id() == b.get_id())
+    };
+    let mut kept = Vec::new();
+    for (flag, flag_args) in defaults {
+        let arg = command.get_arguments().find(|arg| {
+            arg.get_long() == Some(flag.as_str())
+                || arg
+                    .get_all_aliases()
+                    .is_some_and(|aliases| aliases.contains(&flag.as_str()))
+        });
+        // unknown ones are kept, for the real parse to complain about
+        if let Some(arg) = arg {
+            if given.iter().any(|other| {
+                other.get_id() == arg.get_id() || conflicts(arg, other) || conflicts(other, arg)
+            }) {
+                continue;
+            }
         }
+        kept.extend(flag_args);
+    }
+    args.splice(1..1, kept);
+    args
+}
+
+/// The first found of `.mrh.toml` in the current directory,
+/// and `mrh/config.toml` in the user's config directory
+#[cfg(feature = "config")]
+fn config_file() -> Option<PathBuf> {
+    let local = PathBuf::from(".mrh.toml");
+    if local.is_file() {
+        return Some(local);
+    }
+    let global = dirs_next::config_dir()?.join("mrh").join("config.toml");
+    global.is_file().then_some(global)
+}
+
+/// Turn `key = value` lines into the equivalent flags,
+/// like `max-depth = 2` into `--max-depth=2`, along with the flag name
+#[cfg(feature = "config")]
+fn read_config(file: &Path) -> Result<Vec<(String, Vec<OsString>)>> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(why) => bail!("could not read config file {}: {why}", file.display()),
     };
-    let mut output = if let Ok(path) = result.path.strip_prefix(current_dir) {
-        if path == Path::new("") {
-            ".".into()
-        } else {
-            String::from(path.to_string_lossy())
+    let table: toml::Table = match contents.parse() {
+        Ok(table) => table,
+        Err(why) => bail!("could not parse config file {}: {why}", file.display()),
+    };
+    let mut defaults = Vec::new();
+    for (key, value) in table {
+        // allow "max_depth" in place of "max-depth"
+        let name = key.replace('_', "-");
+        let flag = format!("--{name}");
+        let values = match value {
+            toml::Value::Boolean(true) => {
+                defaults.push((name, vec![flag.into()]));
+                continue;
+            }
+            toml::Value::Boolean(false) => continue,
+            toml::Value::Array(values) => values,
+            value => vec![value],
+        };
+        let mut args = Vec::new();
+        for value in values {
+            let value = match value {
+                toml::Value::String(text) => text,
+                toml::Value::Integer(number) => number.to_string(),
+                _ => bail!(
+                    "unsupported value for {key} in config file {}",
+                    file.display()
+                ),
+            };
+            args.push(format!("{flag}={value}").into());
         }
-    } else {
-        String::from(result.path.to_string_lossy())
+        defaults.push((name, args));
+    }
+    Ok(defaults)
+}
+
+fn read_manifest(file: &Path) -> Result<Vec<PathBuf>> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(why) => bail!("could not read manifest file {}: {why}", file.display()),
     };
-    if let Some(pending) = result.pending {
-        let pending: Vec<_> = pending.into_iter().collect();
-        write!(output, " ({})", CYAN.paint(pending.join(", ")))?;
+    // like the output of `ghq list --full-path`
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn write_state(file: &Path, problems: &[PathBuf]) -> Result<()> {
+    let mut contents = String::new();
+    for path in problems {
+        writeln!(contents, "{}", path.display())?;
+    }
+    if let Err(why) = fs::write(file, contents) {
+        bail!("could not write state file {}: {why}", file.display());
+    }
+    Ok(())
+}
+
+fn display_grouped(outputs: impl Iterator<Item = mrh::Output>, cli: &Cli) -> Result<()> {
+    let mut groups: IndexMap<PathBuf, Vec<mrh::Output>> = IndexMap::new();
+    for output in outputs {
+        let dir = match output.path.parent() {
+            Some(dir) if dir != Path::new("") => dir.into(),
+            _ => PathBuf::from("."),
+        };
+        groups.entry(dir).or_default().push(output);
+    }
+    for (dir, outputs) in groups {
+        let clean = outputs
+            .iter()
+            .filter(|output| output.pending.is_none() && output.error.is_none())
+            .count();
+        print_line(&format!(
+            "{}: {}",
+            dir.display(),
+            paint(
+                BRIGHT_BLACK,
+                format!("{clean}/{} clean", outputs.len()),
+                cli
+            ),
+        ));
+        for output in outputs {
+            print_line(&format!("    {}", format_human(output, cli)?));
+        }
+    }
+    Ok(())
+}
+
+fn display_statusline(outputs: impl Iterator<Item = mrh::Output>, pending: &str, error: &str) {
+    let (mut pending_count, mut error_count) = (0, 0);
+    for output in outputs {
+        if output.pending.is_some() {
+            pending_count += 1;
+        }
+        if output.error.is_some() {
+            error_count += 1;
+        }
+    }
+    print_line(&format!("{pending}{pending_count} {error}{error_count}"));
+}
+
+fn format_human(result: mrh::Output, cli: &Cli) -> Result<String> {
+    let mut output = String::from(result.path.to_string_lossy());
+    if let Some(ref branch) = result.branch {
+        write!(
+            output,
+            " {}",
+            paint(BRIGHT_BLACK, format!("[{branch}]"), cli)
+        )?;
+    }
+    if let Some(ref tag) = result.tag {
+        write!(output, " @{tag}")?;
+    }
+    let pending = describe_pending(&result, cli);
+    if !pending.is_empty() {
+        write!(output, " ({})", paint(CYAN, pending.join(", "), cli))?;
+    }
+    for detail in describe_details(&result, cli)? {
+        write!(output, " {}", paint(BRIGHT_BLACK, detail, cli))?;
     }
     if let Some(error) = result.error {
         write!(
             output,
             " ({}: {})",
-            BRIGHT_RED.paint("error"),
-            BRIGHT_BLACK.paint(error.to_string()),
+            paint(BRIGHT_RED, "error", cli),
+            paint(BRIGHT_BLACK, error.to_string(), cli),
+        )?;
+    }
+    Ok(output)
+}
+
+/// Like `git status`, a header line followed by indented details
+fn format_block(result: mrh::Output, cli: &Cli) -> Result<String> {
+    let mut output = String::from(result.path.to_string_lossy());
+    if let Some(ref branch) = result.branch {
+        write!(output, " on {branch}")?;
+    }
+    if let Some(ref tag) = result.tag {
+        write!(output, " @{tag}")?;
+    }
+    for label in describe_pending(&result, cli) {
+        write!(output, "\n    {}", paint(CYAN, label, cli))?;
+    }
+    // too long for the one-line format, where states already hint at these
+    match (result.ahead, result.behind) {
+        (Some(ahead), Some(behind)) if ahead > 0 || behind > 0 => {
+            let counts = format!("{ahead} ahead, {behind} behind upstream");
+            write!(output, "\n    {}", paint(BRIGHT_BLACK, counts, cli))?;
+        }
+        _ => (),
+    }
+    for detail in describe_details(&result, cli)? {
+        write!(output, "\n    {}", paint(BRIGHT_BLACK, detail, cli))?;
+    }
+    if let Some(error) = result.error {
+        write!(
+            output,
+            "\n    {}: {}",
+            paint(BRIGHT_RED, "error", cli),
+            paint(BRIGHT_BLACK, error.to_string(), cli),
         )?;
     }
+    Ok(output)
+}
+
+fn jobs(cli: &Cli) -> usize {
+    // parallel results only come at the end
+    if cli.output_ndjson {
+        return 1;
+    }
+    match cli.jobs {
+        0 => std::thread::available_parallelism().map_or(1, usize::from),
+        n => n,
+    }
+}
+
+fn paint(color: Color, text: impl Into<String>, cli: &Cli) -> String {
+    let text = text.into();
+    if cli.colored {
+        color.paint(text).to_string()
+    } else {
+        text
+    }
+}
+
+/// Replacement wording for pending states, see `--labels`
+#[derive(Clone)]
+struct Labels(HashMap<PendingState, String>);
+
+fn read_labels(file: &str) -> Result<Labels> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(why) => bail!("could not read labels file {file}: {why}"),
+    };
+    let mut labels = HashMap::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let Some((state, label)) = line.split_once('=') else {
+            bail!("expected `state = label`, found: {line}");
+        };
+        // allows "unpushed-commits" in place of "unpushed commits"
+        labels.insert(state.parse()?, label.trim().to_string());
+    }
+    Ok(Labels(labels))
+}
+
+fn label(state: PendingState, cli: &Cli) -> String {
+    match cli.labels {
+        Some(Labels(ref labels)) if labels.contains_key(&state) => labels[&state].clone(),
+        _ => state.to_string(),
+    }
+}
+
+fn describe_pending(result: &mrh::Output, cli: &Cli) -> Vec<String> {
+    let Some(ref pending) = result.pending else {
+        return Vec::new();
+    };
+    pending
+        .iter()
+        .map(|&state| (state, label(state, cli)))
+        .map(|(state, label)| match result.secrets {
+            Some(ref secrets) if state == PendingState::PossibleSecrets => {
+                let secrets: Vec<_> = secrets.iter().map(|path| path.to_string_lossy()).collect();
+                format!("{label}: {}", secrets.join(", "))
+            }
+            _ if state == PendingState::Stale => match result.head_age {
+                Some(age) => format!("{label}: {} days", age.as_secs() / (24 * 60 * 60)),
+                None => label,
+            },
+            _ if state == PendingState::LocalExcludes => match result.local_excludes {
+                Some(ref patterns) => format!("{label}: {}", patterns.join(", ")),
+                None => label,
+            },
+            _ if state == PendingState::UnpushedOtherBranches => match result.unpushed_branches {
+                Some(ref branches) => format!("{label}: {}", branches.join(", ")),
+                None => label,
+            },
+            _ if state == PendingState::UpstreamGone => match result.gone_upstreams {
+                Some(ref branches) => format!("{label}: {}", branches.join(", ")),
+                None => label,
+            },
+            _ if state == PendingState::UntrackedFiles => match result.untracked_count {
+                Some(count) => format!("{label} ({count})"),
+                None => label,
+            },
+            _ => label,
+        })
+        .collect()
+}
+
+fn describe_details(result: &mrh::Output, cli: &Cli) -> Result<Vec<String>> {
+    let mut details = Vec::new();
+    if let (Some((ahead, behind)), Some(refname)) = (result.since_ref, &cli.since_ref) {
+        let mut relation = format!("ahead of {refname} by {ahead}");
+        if behind > 0 {
+            write!(relation, ", behind by {behind}")?;
+        }
+        details.push(relation);
+    }
+    if let Some(ref author) = result.last_author {
+        details.push(format!("by {author}"));
+    }
+    if let Some(ref remote) = result.remote {
+        details.push(format!("vs {remote}"));
+    }
+    Ok(details)
+}
+
+fn print_line(output: &str) {
     if let Err(why) = writeln!(std::io::stdout(), "{output}") {
         if why.kind() == std::io::ErrorKind::BrokenPipe {
             process::exit(1);
@@ -119,38 +1045,355 @@ fn display_human(result: mrh::Output) -> Result<()> {
             eprintln!("{why}");
         }
     }
-    Ok(())
 }
 
 #[cfg(feature = "json")]
-fn make_serde_digestible(result: mrh::Output) -> Output {
-    let path = result.path.to_string_lossy().to_string();
-    let pending = match result.pending {
-        Some(pending) => {
-            let vec: Vec<_> = pending.iter().map(|value| value.to_string()).collect();
-            Some(vec)
-        }
-        None => None,
+fn crawl_over_ssh(target: &str, cli: &Cli) -> Result<Vec<mrh::Output>> {
+    let (host, root_path) = target.split_once(':').unwrap_or((target, "."));
+    let mut args = vec![String::from("mrh"), String::from("--output-json")];
+    for (enabled, flag) in [
+        (cli.recurse_submodule_like, "--recurse-submodule-like"),
+        (cli.follow_links, "--follow-links"),
+        (cli.include_hidden, "--include-hidden"),
+        (cli.report_walk_errors, "--report-walk-errors"),
+        (cli.include_bare, "--include-bare"),
+        (cli.sorted, "--sorted"),
+        (cli.pending, "--pending"),
+        (cli.ignore_untracked, "--ignore-untracked"),
+        (cli.ignore_uncommitted_repos, "--ignore-uncommitted-repos"),
+        (cli.flag_empty, "--flag-empty"),
+        (cli.include_ignored, "--include-ignored"),
+        (cli.flag_state, "--flag-state"),
+        (cli.absolute_paths, "--absolute-paths"),
+        (cli.untagged_heads, "--untagged-heads"),
+        (cli.flag_dangling_tags, "--flag-dangling-tags"),
+        (cli.flag_shallow, "--flag-shallow"),
+        (cli.detached_head, "--detached-head"),
+        (cli.off_default_branch, "--off-default-branch"),
+        (cli.show_tag, "--show-tag"),
+        (cli.show_author, "--show-author"),
+        (cli.include_stashed, "--include-stashed"),
+        (cli.flag_whitespace, "--flag-whitespace"),
+        (cli.flag_wip, "--flag-wip"),
+        (cli.require_signed, "--require-signed"),
+        (cli.collapse_diverged, "--collapse-diverged"),
+        (cli.combine_changes, "--combine-changes"),
+        (cli.local_excludes, "--local-excludes"),
+        (cli.check_all_branches, "--check-all-branches"),
+        (cli.include_submodules, "--include-submodules"),
+        (cli.local_remotes, "--local-remotes"),
+        (cli.flag_no_remote, "--flag-no-remote"),
+        (cli.flag_no_upstream, "--flag-no-upstream"),
+        (cli.flag_gone_upstream, "--flag-gone-upstream"),
+        (cli.warn_secrets, "--warn-secrets"),
+        (cli.all_remotes, "--all-remotes"),
+        (cli.fetch, "--fetch"),
+        (cli.flag_prune, "--flag-prune"),
+        (cli.flag_default_renamed, "--flag-default-renamed"),
+    ] {
+        if enabled {
+            args.push(flag.into());
+        }
+    }
+    if let Some(depth) = cli.max_depth {
+        args.push(format!("--max-depth={depth}"));
+    }
+    if let Some(max) = cli.limit {
+        args.push(format!("--limit={max}"));
+    }
+    // with 0, the remote host counts its own CPUs
+    args.push(format!("--jobs={}", cli.jobs));
+    if let Some(ref name) = cli.on_branch {
+        args.push(format!("--on-branch={name}"));
+    }
+    if let Some(days) = cli.recent_resets {
+        args.push(format!("--recent-resets={days}"));
+    }
+    if let Some(days) = cli.stale_after {
+        args.push(format!("--stale-after={days}"));
+    }
+    for pattern in &cli.exclude {
+        args.push(format!("--exclude={pattern}"));
+    }
+    for pattern in &cli.path_glob {
+        args.push(format!("--match={pattern}"));
+    }
+    for state in &cli.only {
+        args.push(format!("--only={state}"));
+    }
+    for state in &cli.ignore {
+        args.push(format!("--ignore={state}"));
+    }
+    for pattern in &cli.wip_pattern {
+        args.push(format!("--wip-pattern={pattern}"));
+    }
+    for pattern in &cli.secret_pattern {
+        args.push(format!("--secret-pattern={pattern}"));
+    }
+    if let Some(ref refname) = cli.compare_against {
+        args.push(format!("--compare-against={refname}"));
+    }
+    if let Some(ref refname) = cli.since_ref {
+        args.push(format!("--since-ref={refname}"));
+    }
+    if let Some(ref method) = cli.ssh_auth_method {
+        args.push(format!("--ssh-auth-method={method}"));
+    }
+    args.push(format!("--remote-name={}", cli.remote_name));
+    if let Some(ref path) = cli.ssh_key {
+        args.push(format!("--ssh-key={}", path.display()));
+    }
+    if let Some(secs) = cli.remote_timeout {
+        args.push(format!("--remote-timeout={secs}"));
+    }
+    if let (Some(secs), false) = (cli.cache_ttl, cli.no_cache) {
+        args.push(format!("--cache-ttl={secs}"));
+    }
+    args.push(root_path.into());
+    let mut command = process::Command::new("ssh");
+    // ssh joins arguments with spaces, for the remote shell to split again
+    command
+        .arg(host)
+        .args(args.iter().map(|arg| shell_quote(arg)))
+        .stderr(process::Stdio::inherit());
+    let result = command.output()?;
+    ensure!(
+        result.status.success(),
+        "ssh to {host} failed: {}",
+        result.status
+    );
+    let mut outputs = Vec::new();
+    for line in String::from_utf8_lossy(&result.stdout).lines() {
+        let output: Output = serde_json::from_str(line)?;
+        outputs.push(mrh::Output {
+            path: output.path.into(),
+            branch: output.branch,
+            pending: output
+                .pending
+                .map(|pending| pending.iter().map(|label| label.parse()).collect())
+                .transpose()?,
+            error: output
+                .error
+                .map(|error| git2::Error::from_str(&error).into()),
+            remote: output.remote,
+            remote_host: output.remote_host,
+            ahead: output.ahead,
+            behind: output.behind,
+            secrets: output
+                .secrets
+                .map(|secrets| secrets.into_iter().map(PathBuf::from).collect()),
+            since_ref: output
+                .since_ref
+                .map(|since_ref| (since_ref.ahead, since_ref.behind)),
+            tag: output.tag,
+            last_author: output.last_author,
+            local_excludes: output.local_excludes,
+            head_age: output.head_age_secs.map(Duration::from_secs),
+            untracked_count: output.untracked_count,
+            unpushed_branches: output.unpushed_branches,
+            gone_upstreams: output.gone_upstreams,
+        });
+    }
+    Ok(outputs)
+}
+
+/// Single-quoted for a POSIX shell, where only `'` itself needs escaping
+#[cfg(feature = "json")]
+fn shell_quote(arg: &str) -> String {
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+#[cfg(not(feature = "json"))]
+fn crawl_over_ssh(_: &str, _: &Cli) -> Result<Vec<mrh::Output>> {
+    bail!("Support for JSON output format not compiled in");
+}
+
+#[cfg(feature = "dashboard")]
+fn run_dashboard(cli: &Cli) -> Result<()> {
+    use crossterm::{cursor, execute, terminal};
+
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, terminal::EnterAlternateScreen, cursor::Hide)?;
+    let result = dashboard_loop(cli, &mut stdout);
+    execute!(stdout, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+#[cfg(not(feature = "dashboard"))]
+fn run_dashboard(_: &Cli) -> Result<()> {
+    bail!("Support for the dashboard not compiled in");
+}
+
+#[cfg(feature = "dashboard")]
+fn dashboard_loop(cli: &Cli, stdout: &mut io::Stdout) -> Result<()> {
+    use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
+    use std::time::Instant;
+
+    let refresh = Duration::from_secs(cli.refresh);
+    loop {
+        let mut outputs: Vec<_> = crawler(cli).collect_parallel();
+        // most pending first, then errors, then clean ones
+        outputs.sort_by_key(|output| {
+            let pending = output.pending.as_ref().map_or(0, |pending| pending.len());
+            (std::cmp::Reverse(pending), output.error.is_none())
+        });
+        render_dashboard(stdout, &outputs, cli)?;
+        let refreshed = Instant::now();
+        while let Some(timeout) = refresh.checked_sub(refreshed.elapsed()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            match event::read()? {
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('q') | KeyCode::Esc,
+                    ..
+                }) => return Ok(()),
+                Event::Key(KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers,
+                    ..
+                }) if modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                Event::Resize(..) => render_dashboard(stdout, &outputs, cli)?,
+                _ => (),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "dashboard")]
+fn render_dashboard(stdout: &mut io::Stdout, outputs: &[mrh::Output], cli: &Cli) -> Result<()> {
+    use crossterm::{
+        cursor, queue,
+        style::{Print, PrintStyledContent, Stylize},
+        terminal,
     };
-    let error = result.error.map(|error| error.to_string());
-    Output {
-        path,
-        pending,
-        error,
+
+    let (width, height) = terminal::size()?;
+    let width = usize::from(width);
+    let truncate = |text: String, used: usize| -> String {
+        text.chars().take(width.saturating_sub(used)).collect()
+    };
+    let pending = outputs.iter().filter(|output| output.pending.is_some());
+    let errors = outputs.iter().filter(|output| output.error.is_some());
+    queue!(
+        stdout,
+        terminal::Clear(terminal::ClearType::All),
+        cursor::MoveTo(0, 0),
+        PrintStyledContent(
+            truncate(
+                format!(
+                    "{} repos, {} pending, {} errors (q to quit)",
+                    outputs.len(),
+                    pending.count(),
+                    errors.count(),
+                ),
+                0,
+            )
+            .bold()
+        ),
+    )?;
+    for (row, output) in (1..height).zip(outputs) {
+        let path = truncate(output.path.to_string_lossy().into(), 0);
+        let used = path.chars().count();
+        queue!(stdout, cursor::MoveTo(0, row), Print(path))?;
+        if output.pending.is_some() {
+            let labels = describe_pending(output, cli);
+            let labels = truncate(format!(" ({})", labels.join(", ")), used);
+            queue!(stdout, PrintStyledContent(labels.cyan()))?;
+        }
+        if let Some(ref error) = output.error {
+            // the bare message is enough, given the little room there is
+            let error = match error {
+                mrh::MrhError::Git(why) => why.message().to_string(),
+                other => other.to_string(),
+            };
+            let error = truncate(format!(" (error: {error})"), used);
+            queue!(stdout, PrintStyledContent(error.red()))?;
+        }
+    }
+    stdout.flush()?;
+    Ok(())
+}
+
+fn display_summary(summary: &Summary, json: bool) -> Result<()> {
+    if json {
+        return display_summary_json(summary);
     }
+    let Summary {
+        total: repos,
+        pending,
+        errors,
+        ..
+    } = summary;
+    let plural = |count: &usize| if *count == 1 { "" } else { "s" };
+    eprintln!(
+        "{repos} repo{} scanned, {pending} pending, {errors} error{}",
+        plural(repos),
+        plural(errors),
+    );
+    Ok(())
+}
+
+#[cfg(feature = "json")]
+fn display_summary_json(summary: &Summary) -> Result<()> {
+    let Summary {
+        total: repos,
+        pending,
+        errors,
+        ..
+    } = summary;
+    print_line(&serde_json::to_string(&serde_json::json!({
+        "schema_version": mrh::SCHEMA_VERSION,
+        "summary": { "repos": repos, "pending": pending, "errors": errors },
+    }))?);
+    Ok(())
+}
+#[cfg(not(feature = "json"))]
+fn display_summary_json(_: &Summary) -> Result<()> {
+    bail!("Support for JSON output format not compiled in");
 }
 
 #[cfg(feature = "json")]
-fn display_json(output: mrh::Output) {
-    let output = make_serde_digestible(output);
-    if let Err(why) = serde_json::to_writer(std::io::stdout(), &output) {
-        eprintln!("{why}");
-        process::exit(1);
+fn display_json(output: mrh::Output, flush: bool) {
+    match serde_json::to_string(&output) {
+        Ok(line) => print_line(&line),
+        Err(why) => {
+            eprintln!("{why}");
+            process::exit(1);
+        }
+    }
+    if flush {
+        let _ = io::stdout().flush();
+    }
+}
+#[cfg(feature = "yaml")]
+fn display_yaml(outputs: Vec<mrh::Output>) -> Result<()> {
+    print!("{}", serde_yaml::to_string(&outputs)?);
+    Ok(())
+}
+#[cfg(not(feature = "yaml"))]
+fn display_yaml(_: Vec<mrh::Output>) -> Result<()> {
+    bail!("Support for YAML output format not compiled in");
+}
+
+#[cfg(feature = "toml")]
+fn display_toml(outputs: Vec<mrh::Output>) -> Result<()> {
+    // TOML has no top-level arrays
+    #[derive(Serialize)]
+    struct Repos {
+        repos: Vec<mrh::Output>,
     }
-    println!();
+    print!("{}", toml::to_string(&Repos { repos: outputs })?);
+    Ok(())
 }
+#[cfg(not(feature = "toml"))]
+fn display_toml(_: Vec<mrh::Output>) -> Result<()> {
+    bail!("Support for TOML output format not compiled in");
+}
+
 #[cfg(not(feature = "json"))]
-fn display_json(_: mrh::Output) {
+fn display_json(_: mrh::Output, _: bool) {
     eprintln!("Support for JSON output format not compiled in");
     process::exit(1);
 }
@@ -0,0 +1,110 @@
+//! On-disk cache of where repos were found, see `Crawler::path_cache`
+//!
+//! The cache records the modification time of every directory walked.
+//! Adding or removing an entry (like a `.git` directory) changes the
+//! modification time of its parent, so if none of the times changed,
+//! the repos found are still the same, and the walk can be skipped.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+const HEADER: &str = "mrh-path-cache 1";
+
+/// Repo paths from the cache, if it is still valid for this `key`
+pub fn load(file: &Path, key: &str) -> Option<Vec<PathBuf>> {
+    let contents = fs::read_to_string(file).ok()?;
+    let mut lines = contents.lines();
+    if lines.next()? != HEADER || lines.next()?.strip_prefix("key ")? != key {
+        return None;
+    }
+    let mut repos = Vec::new();
+    for line in lines {
+        let (kind, rest) = line.split_once(' ')?;
+        match kind {
+            "D" => {
+                let (mtime, path) = rest.split_once(' ')?;
+                if mtime_of(Path::new(path))? != mtime.parse().ok()? {
+                    return None;
+                }
+            }
+            "R" => repos.push(rest.into()),
+            _ => return None,
+        }
+    }
+    Some(repos)
+}
+
+/// Passes paths through, noting them down, and writes the cache
+/// once the walk is complete
+pub struct Recorder<I> {
+    inner: I,
+    file: PathBuf,
+    key: String,
+    dirs: Vec<(PathBuf, u128)>,
+    repos: Vec<PathBuf>,
+    saved: bool,
+}
+
+impl<I> Recorder<I> {
+    pub fn new(inner: I, file: &Path, key: String) -> Self {
+        Self {
+            inner,
+            file: file.into(),
+            key,
+            dirs: Vec::new(),
+            repos: Vec::new(),
+            saved: false,
+        }
+    }
+
+    fn save(&mut self) {
+        if self.saved {
+            return;
+        }
+        self.saved = true;
+        let mut contents = format!("{HEADER}\nkey {}\n", self.key);
+        for (path, mtime) in &self.dirs {
+            contents.push_str(&format!("D {mtime} {}\n", path.display()));
+        }
+        for path in &self.repos {
+            contents.push_str(&format!("R {}\n", path.display()));
+        }
+        // a cache that can't be written is simply not used next time
+        let _ = fs::write(&self.file, contents);
+    }
+}
+
+impl<I: Iterator<Item = PathBuf>> Iterator for Recorder<I> {
+    type Item = PathBuf;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.inner.next() {
+            Some(path) => {
+                if let Some(mtime) = mtime_of(&path) {
+                    self.dirs.push((path.clone(), mtime));
+                }
+                if path.join(".git").exists() || is_bare_repo(&path) {
+                    self.repos.push(path.clone());
+                }
+                Some(path)
+            }
+            None => {
+                self.save();
+                None
+            }
+        }
+    }
+}
+
+// the same heuristic git uses to recognize a repo directory
+fn is_bare_repo(path: &Path) -> bool {
+    path.join("HEAD").is_file() && path.join("objects").is_dir() && path.join("refs").is_dir()
+}
+
+fn mtime_of(path: &Path) -> Option<u128> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(since_epoch.as_nanos())
+}
@@ -0,0 +1,156 @@
+//! On-disk cache of what remotes had to say, see `Crawler::remote_cache`
+//!
+//! Each entry is for a repo's git dir and HEAD commit, since a HEAD that
+//! moved can compare differently against the remote, like being ahead.
+//! Entries older than the TTL are not used, and are dropped on save.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::PendingState;
+
+/// Bumped whenever the file's shape changes, so old files are ignored
+const VERSION: u32 = 1;
+
+/// The pending states (and remote) added by checking the remote
+#[derive(Clone)]
+pub struct Entry {
+    pub states: Vec<PendingState>,
+    pub remote: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct File {
+    version: u32,
+    key: String,
+    entries: Vec<SavedEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedEntry {
+    path: PathBuf,
+    head: String,
+    saved: u64,
+    remote: Option<String>,
+    states: Vec<String>,
+}
+
+type Entries = HashMap<(PathBuf, git2::Oid), (u64, Entry)>;
+
+pub struct RemoteCache {
+    file: PathBuf,
+    ttl: Duration,
+    // read on first use, along with the key it's read for
+    loaded: Option<(String, Entries)>,
+    changed: bool,
+}
+
+impl RemoteCache {
+    pub const fn new(file: PathBuf, ttl: Duration) -> Self {
+        Self {
+            file,
+            ttl,
+            loaded: None,
+            changed: false,
+        }
+    }
+
+    /// Entries from the cache, unless it was made with another `key`
+    fn entries(&mut self, key: &str) -> &mut Entries {
+        let (_, entries) = self
+            .loaded
+            .get_or_insert_with(|| (key.into(), load(&self.file, key)));
+        entries
+    }
+
+    pub fn get(&mut self, key: &str, git_dir: &Path, head: git2::Oid) -> Option<Entry> {
+        let ttl = self.ttl.as_secs();
+        let (saved, entry) = self.entries(key).get(&(git_dir.into(), head))?;
+        (now().saturating_sub(*saved) < ttl).then(|| entry.clone())
+    }
+
+    pub fn insert(&mut self, key: &str, git_dir: &Path, head: git2::Oid, entry: Entry) {
+        self.entries(key)
+            .insert((git_dir.into(), head), (now(), entry));
+        self.changed = true;
+    }
+
+    /// Write the entries, unless there is nothing new since loading
+    pub fn save(&mut self) {
+        let Some((ref key, ref entries)) = self.loaded else {
+            return;
+        };
+        if !self.changed {
+            return;
+        }
+        self.changed = false;
+        let entries = entries
+            .iter()
+            .filter(|(_, (saved, _))| now().saturating_sub(*saved) < self.ttl.as_secs())
+            .map(|((path, head), (saved, entry))| SavedEntry {
+                path: path.clone(),
+                head: head.to_string(),
+                saved: *saved,
+                remote: entry.remote.clone(),
+                states: entry.states.iter().map(ToString::to_string).collect(),
+            })
+            .collect();
+        let file = File {
+            version: VERSION,
+            key: key.clone(),
+            entries,
+        };
+        // a cache that can't be written is simply not used next time
+        if let Ok(contents) = serde_json::to_string(&file) {
+            let _ = fs::write(&self.file, contents);
+        }
+    }
+}
+
+impl Drop for RemoteCache {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+fn load(file: &Path, key: &str) -> Entries {
+    let Ok(contents) = fs::read_to_string(file) else {
+        return Entries::new();
+    };
+    let file: File = match serde_json::from_str(&contents) {
+        Ok(file) => file,
+        Err(_) => return Entries::new(),
+    };
+    if file.version != VERSION || file.key != key {
+        return Entries::new();
+    }
+    file.entries.into_iter().filter_map(parse).collect()
+}
+
+// a damaged entry only loses itself
+fn parse(saved: SavedEntry) -> Option<((PathBuf, git2::Oid), (u64, Entry))> {
+    let head = git2::Oid::from_str(&saved.head).ok()?;
+    let states = saved
+        .states
+        .iter()
+        .map(|name| name.parse().ok())
+        .collect::<Option<_>>()?;
+    let entry = Entry {
+        states,
+        remote: saved.remote,
+    };
+    Some(((saved.path, head), (saved.saved, entry)))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
@@ -0,0 +1,55 @@
+#![cfg(feature = "cli")]
+
+mod common;
+
+use std::{path::Path, process::Command};
+
+use common::{repo, scratch, with_origin, write};
+
+/// The CLI, to run in `dir`, with `home` as the user's home directory
+fn mrh(dir: &Path, home: &Path) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_mrh"));
+    command
+        .current_dir(dir)
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env_remove("NO_COLOR");
+    command
+}
+
+fn stdout(command: &mut Command) -> String {
+    let output = command.output().unwrap();
+    assert!(
+        output.status.success(),
+        "{command:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn unreadable_git_config_is_reported_per_repo() {
+    let root = scratch("bad-git-config");
+    let repos = root.join("repos");
+    for name in ["a", "b"] {
+        let dir = repo(&repos.join(name));
+        with_origin(&dir, &root.join(format!("{name}.git")));
+    }
+    let home = root.join("home");
+    write(&home.join(".gitconfig"), "[broken\n");
+    let output = stdout(mrh(&repos, &home).args(["--ssh-auth-method", "ssh-agent", "--sorted"]));
+    let lines: Vec<_> = output.lines().collect();
+    assert_eq!(lines.len(), 2, "{output}");
+    assert!(lines[0].starts_with("a ") && lines[0].contains("error: failed to parse config"));
+    assert!(lines[1].starts_with("b ") && lines[1].contains("error: failed to parse config"));
+}
+
+#[test]
+fn no_color_leaves_out_escape_sequences() {
+    let root = scratch("no-color");
+    write(&repo(&root.join("a")).join("new"), "");
+    let colored = stdout(mrh(&root, &root).arg("--color=always"));
+    assert!(colored.contains('\x1b'), "{colored}");
+    let plain = stdout(mrh(&root, &root).env("NO_COLOR", "1"));
+    assert_eq!(plain, "a [main] (untracked files (1))\n");
+}
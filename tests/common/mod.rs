@@ -0,0 +1,97 @@
+//! Helpers for building repos to crawl, using the `git` CLI
+
+// each test crate uses only some of these
+#![allow(dead_code)]
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// An empty directory, unique to the calling test
+pub fn scratch(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("mrh-test-{}-{name}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Run git in `dir`, unaffected by the user's config, returning stdout
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = run_git(dir, args);
+    assert!(
+        output.status.success(),
+        "git {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Like [`git`], for commands expected to fail, like a conflicting merge
+pub fn git_failing(dir: &Path, args: &[&str]) {
+    let output = run_git(dir, args);
+    assert!(!output.status.success(), "git {args:?} succeeded");
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> std::process::Output {
+    Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .env("GIT_CONFIG_GLOBAL", dir.join(".no-global-config"))
+        .env("GIT_CONFIG_NOSYSTEM", "1")
+        .env("GIT_AUTHOR_NAME", "mrh")
+        .env("GIT_AUTHOR_EMAIL", "mrh@example.com")
+        .env("GIT_COMMITTER_NAME", "mrh")
+        .env("GIT_COMMITTER_EMAIL", "mrh@example.com")
+        .output()
+        .unwrap()
+}
+
+/// A repo on branch `main`, with a single commit
+pub fn repo(dir: &Path) -> PathBuf {
+    fs::create_dir_all(dir).unwrap();
+    git(dir, &["init", "-q", "-b", "main"]);
+    commit(dir, "initial");
+    dir.into()
+}
+
+pub fn commit(dir: &Path, message: &str) {
+    git(dir, &["commit", "-q", "--allow-empty", "-m", message]);
+}
+
+/// Commit `name` (relative to `dir`) with these contents
+pub fn commit_file(dir: &Path, name: &str, contents: &str) {
+    write(&dir.join(name), contents);
+    git(dir, &["add", name]);
+    commit(dir, &format!("change {name}"));
+}
+
+/// A clone of `origin` at `dir`
+pub fn clone(origin: &Path, dir: &Path) -> PathBuf {
+    let (origin, dir_arg) = (origin.to_str().unwrap(), dir.to_str().unwrap());
+    git(origin.as_ref(), &["clone", "-q", origin, dir_arg]);
+    dir.into()
+}
+
+/// Add `library` to `app` as a submodule at `path`, and commit that
+pub fn submodule(app: &Path, library: &Path, path: &str) {
+    let url = library.to_str().unwrap();
+    // local clones are otherwise refused for submodules
+    let allow = "protocol.file.allow=always";
+    git(app, &["-c", allow, "submodule", "add", "-q", url, path]);
+    git(app, &["commit", "-q", "-m", "add submodule"]);
+}
+
+/// A bare repo that `dir` pushes `main` to, and tracks, as `origin`
+pub fn with_origin(dir: &Path, bare: &Path) {
+    git(dir, &["clone", "-q", "--bare", ".", bare.to_str().unwrap()]);
+    git(dir, &["remote", "add", "origin", bare.to_str().unwrap()]);
+    git(dir, &["fetch", "-q", "origin"]);
+    git(dir, &["branch", "-q", "--set-upstream-to=origin/main"]);
+}
+
+pub fn write(path: &Path, contents: &str) {
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
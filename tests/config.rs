@@ -0,0 +1,83 @@
+//! Precedence between the config file and the command line
+
+#![cfg(feature = "config")]
+
+mod common;
+
+use std::{path::Path, process::Command};
+
+use common::{repo, scratch, write};
+
+/// Run the CLI in `dir`, where it also looks for `.mrh.toml`
+fn mrh(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_mrh"))
+        .current_dir(dir)
+        .args(args)
+        // away from the user's own config file
+        .env("XDG_CONFIG_HOME", dir.join(".config"))
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "mrh {args:?} failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// Repo paths, the first word of each line of the default output
+fn paths(output: &str) -> Vec<&str> {
+    let mut paths: Vec<_> = output
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    paths.sort_unstable();
+    paths
+}
+
+#[test]
+fn config_flags_apply_when_not_on_command_line() {
+    let root = scratch("config-applies");
+    repo(&root.join("a"));
+    repo(&root.join("b"));
+    write(&root.join(".mrh.toml"), "exclude = [\"a\"]\n");
+    assert_eq!(paths(&mrh(&root, &[])), ["b"]);
+}
+
+#[test]
+fn command_line_replaces_repeatable_config_flag() {
+    let root = scratch("config-repeatable");
+    repo(&root.join("a"));
+    repo(&root.join("b"));
+    repo(&root.join("c"));
+    write(&root.join(".mrh.toml"), "exclude = [\"a\"]\n");
+    assert_eq!(paths(&mrh(&root, &["--exclude", "b"])), ["a", "c"]);
+}
+
+#[test]
+fn command_line_replaces_config_value() {
+    let root = scratch("config-value");
+    repo(&root.join("a"));
+    repo(&root.join("deep/b"));
+    write(&root.join(".mrh.toml"), "max-depth = 1\n");
+    assert_eq!(paths(&mrh(&root, &[])), ["a"]);
+    assert_eq!(paths(&mrh(&root, &["--max-depth", "2"])), ["a", "deep/b"]);
+}
+
+#[test]
+fn boolean_in_both_config_and_command_line() {
+    let root = scratch("config-boolean");
+    repo(&root.join("a"));
+    write(&root.join(".mrh.toml"), "sorted = true\n");
+    assert_eq!(paths(&mrh(&root, &["--sorted"])), ["a"]);
+}
+
+#[cfg(all(feature = "json", feature = "yaml"))]
+#[test]
+fn command_line_output_mode_replaces_conflicting_config_one() {
+    let root = scratch("config-output");
+    repo(&root.join("a"));
+    write(&root.join(".mrh.toml"), "output-json = true\n");
+    assert!(mrh(&root, &[]).starts_with('{'));
+    assert!(mrh(&root, &["--output-yaml"]).starts_with("- "));
+}
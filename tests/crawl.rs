@@ -0,0 +1,338 @@
+mod common;
+
+use std::path::{Path, PathBuf};
+
+use common::{clone, commit, commit_file, git, git_failing, repo, scratch, submodule, write};
+use mrh::{Crawler, PendingState};
+
+fn paths(crawler: Crawler) -> Vec<PathBuf> {
+    crawler.map(|output| output.path).collect()
+}
+
+/// Pending states of the only repo found
+fn states(crawler: Crawler) -> Vec<PendingState> {
+    let outputs: Vec<_> = crawler.collect();
+    assert_eq!(outputs.len(), 1, "{outputs:?}");
+    assert!(outputs[0].error.is_none(), "{outputs:?}");
+    outputs[0].pending.iter().flatten().copied().collect()
+}
+
+#[test]
+fn on_branch_yields_only_matching_repos() {
+    let root = scratch("on-branch");
+    repo(&root.join("a"));
+    let b = repo(&root.join("b"));
+    git(&b, &["switch", "-q", "-c", "release"]);
+    let crawler = Crawler::new(&root).on_branch("release".into());
+    assert_eq!(paths(crawler), [PathBuf::from("b")]);
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_links_survives_a_cycle() {
+    let root = scratch("follow-links");
+    repo(&root.join("a"));
+    std::fs::create_dir(root.join("b")).unwrap();
+    std::os::unix::fs::symlink(&root, root.join("b/back")).unwrap();
+    // only reachable through a link
+    let elsewhere = scratch("follow-links-target");
+    repo(&elsewhere.join("d"));
+    std::os::unix::fs::symlink(&elsewhere, root.join("c")).unwrap();
+    let crawler = Crawler::new(&root).follow_links(true).sorted(true);
+    // shown where it really is, outside the root
+    let linked = elsewhere.join("d").canonicalize().unwrap();
+    assert_eq!(paths(crawler), [linked, PathBuf::from("a")]);
+}
+
+#[test]
+fn repos_under_excluded_dirs_are_skipped() {
+    let root = scratch("exclude-dirs");
+    repo(&root.join("a"));
+    repo(&root.join("node_modules/deep/b"));
+    let crawler = Crawler::new(&root).exclude_dirs(vec!["node_*".into()]);
+    assert_eq!(paths(crawler), [PathBuf::from("a")]);
+}
+
+#[test]
+fn sorted_paths_are_lexicographic() {
+    let root = scratch("sorted");
+    for name in ["m", "c/z", "x", "a", "c/b"] {
+        repo(&root.join(name));
+    }
+    let crawler = Crawler::new(&root).sorted(true);
+    assert_eq!(
+        paths(crawler),
+        ["a", "c/b", "c/z", "m", "x"].map(PathBuf::from)
+    );
+}
+
+#[test]
+fn feature_branch_is_not_on_default_branch() {
+    let root = scratch("off-default-branch");
+    let upstream = repo(&root.join("upstream"));
+    let work = clone(&upstream, &root.join("work"));
+    let crawl = || Crawler::new(&work).off_default_branch(true);
+    assert!(!states(crawl()).contains(&PendingState::NotOnDefaultBranch));
+    git(&work, &["switch", "-q", "-c", "feature"]);
+    assert!(states(crawl()).contains(&PendingState::NotOnDefaultBranch));
+}
+
+#[test]
+fn each_root_is_crawled() {
+    let (work, oss) = (scratch("multi-root-work"), scratch("multi-root-oss"));
+    let a = repo(&work.join("a"));
+    let b = repo(&oss.join("b"));
+    // absolute, since relative ones could be from either root
+    let found: Vec<_> = paths(Crawler::new_multi([work, oss]))
+        .iter()
+        .map(|path| path.canonicalize().unwrap())
+        .collect();
+    assert_eq!(found, [a, b].map(|path| path.canonicalize().unwrap()));
+}
+
+#[test]
+fn conflicting_merge_is_in_progress() {
+    let dir = repo(&scratch("merge-in-progress").join("a"));
+    commit_file(&dir, "file", "base\n");
+    git(&dir, &["switch", "-q", "-c", "other"]);
+    commit_file(&dir, "file", "theirs\n");
+    git(&dir, &["switch", "-q", "main"]);
+    commit_file(&dir, "file", "ours\n");
+    git_failing(&dir, &["merge", "-q", "other"]);
+    let states = states(Crawler::new(&dir).flag_repo_state(true));
+    assert!(
+        states.contains(&PendingState::MergeInProgress),
+        "{states:?}"
+    );
+}
+
+#[test]
+fn staged_and_unstaged_changes_are_told_apart() {
+    let dir = repo(&scratch("staged-unstaged").join("a"));
+    commit_file(&dir, "staged", "1\n");
+    commit_file(&dir, "modified", "1\n");
+    write(&dir.join("staged"), "2\n");
+    git(&dir, &["add", "staged"]);
+    write(&dir.join("modified"), "2\n");
+    let states = states(Crawler::new(&dir));
+    assert!(states.contains(&PendingState::StagedChanges), "{states:?}");
+    assert!(
+        states.contains(&PendingState::UnstagedChanges),
+        "{states:?}"
+    );
+}
+
+#[test]
+fn worktrees_have_their_own_status() {
+    let root = scratch("worktrees");
+    let main = repo(&root.join("main"));
+    git(&main, &["worktree", "add", "-q", "-b", "side", "../linked"]);
+    write(&root.join("linked/new"), "");
+    let outputs: Vec<_> = Crawler::new(&root).sorted(true).collect();
+    assert_eq!(outputs.len(), 2, "{outputs:?}");
+    assert_eq!(outputs[0].path, PathBuf::from("linked"));
+    assert_eq!(outputs[0].branch.as_deref(), Some("side"));
+    let pending = outputs[0].pending.as_ref().unwrap();
+    assert!(pending.contains(&PendingState::UntrackedFiles));
+    assert_eq!(outputs[1].path, PathBuf::from("main"));
+    assert_eq!(outputs[1].branch.as_deref(), Some("main"));
+    assert!(outputs[1].pending.is_none(), "{outputs:?}");
+}
+
+#[test]
+fn core_excludesfile_is_respected() {
+    let root = scratch("excludesfile");
+    let dir = repo(&root.join("a"));
+    let excludes = root.join("excludes");
+    write(&excludes, "*.log\n");
+    git(
+        &dir,
+        &["config", "core.excludesfile", excludes.to_str().unwrap()],
+    );
+    write(&dir.join("debug.log"), "");
+    assert_eq!(states(Crawler::new(&dir)), []);
+    write(&dir.join("notes"), "");
+    assert_eq!(states(Crawler::new(&dir)), [PendingState::UntrackedFiles]);
+}
+
+#[test]
+fn only_states_excludes_other_pending_repos() {
+    let root = scratch("only-states");
+    let upstream = repo(&root.join("upstream"));
+    let outdated = clone(&upstream, &root.join("repos/outdated"));
+    commit(&upstream, "newer");
+    git(&outdated, &["fetch", "-q"]);
+    write(&repo(&root.join("repos/untracked")).join("new"), "");
+    let crawler = Crawler::new(root.join("repos"))
+        .pending(true)
+        .only_states(vec![PendingState::OutdatedBranch]);
+    assert_eq!(paths(crawler), [PathBuf::from("outdated")]);
+}
+
+#[test]
+fn staged_trailing_whitespace_is_flagged() {
+    let dir = repo(&scratch("whitespace").join("a"));
+    let crawl = || Crawler::new(&dir).flag_whitespace(true);
+    write(&dir.join("clean"), "fine\n");
+    git(&dir, &["add", "clean"]);
+    assert!(!states(crawl()).contains(&PendingState::WhitespaceErrors));
+    write(&dir.join("dirty"), "trailing \n");
+    git(&dir, &["add", "dirty"]);
+    assert!(states(crawl()).contains(&PendingState::WhitespaceErrors));
+}
+
+#[test]
+fn submodule_with_git_dir_elsewhere_is_found_once() {
+    let root = scratch("submodule-git-dir");
+    let library = repo(&root.join("library"));
+    let app = repo(&root.join("repos/app"));
+    submodule(&app, &library, "lib");
+    // its git dir is under app/.git/modules, which is not a repo of its own
+    let crawler = Crawler::new(root.join("repos"))
+        .recurse_submodule_like(true)
+        .sorted(true);
+    assert_eq!(paths(crawler), ["app", "app/lib"].map(PathBuf::from));
+}
+
+#[test]
+fn shallow_clone_is_flagged() {
+    let root = scratch("shallow");
+    let upstream = repo(&root.join("upstream"));
+    commit(&upstream, "second");
+    let url = upstream.to_str().unwrap();
+    git(
+        &root,
+        &["clone", "-q", "--no-local", "--depth=1", url, "shallow"],
+    );
+    let crawler = Crawler::new(root.join("shallow")).flag_shallow(true);
+    assert_eq!(states(crawler), [PendingState::ShallowClone]);
+}
+
+#[test]
+fn limit_counts_only_yielded_repos() {
+    let root = scratch("pending-limit");
+    for name in ["a", "b", "c"] {
+        repo(&root.join(name));
+    }
+    for name in ["d", "e"] {
+        write(&repo(&root.join(name)).join("new"), "");
+    }
+    // sorted, so the clean ones are inspected first
+    let crawler = Crawler::new(&root).sorted(true).pending(true).limit(2);
+    assert_eq!(paths(crawler), ["d", "e"].map(PathBuf::from));
+}
+
+#[test]
+fn bumped_submodule_pointer_is_flagged() {
+    let root = scratch("submodule-pointer");
+    let library = repo(&root.join("library"));
+    let app = repo(&root.join("app"));
+    submodule(&app, &library, "lib");
+    assert_eq!(states(Crawler::new(&app)), []);
+    commit(&app.join("lib"), "bump");
+    let states = states(Crawler::new(&app));
+    assert!(
+        states.contains(&PendingState::SubmodulePointerChanged),
+        "{states:?}"
+    );
+}
+
+/// Commit on top of HEAD with a made-up signature, which is enough,
+/// since signatures are only checked for being there
+fn signed_commit(dir: &Path) {
+    let tree = git(dir, &["rev-parse", "HEAD^{tree}"]);
+    let parent = git(dir, &["rev-parse", "HEAD"]);
+    let person = "mrh <mrh@example.com> 1700000000 +0000";
+    let object = format!(
+        "tree {}\nparent {}\nauthor {person}\ncommitter {person}\n\
+         gpgsig -----BEGIN SSH SIGNATURE-----\n made-up\n -----END SSH SIGNATURE-----\n\
+         \nsigned\n",
+        tree.trim(),
+        parent.trim(),
+    );
+    let file = dir.join(".git/signed-commit");
+    write(&file, &object);
+    let oid = git(
+        dir,
+        &["hash-object", "-t", "commit", "-w", file.to_str().unwrap()],
+    );
+    git(dir, &["update-ref", "HEAD", oid.trim()]);
+}
+
+#[test]
+fn unsigned_unpushed_commits_are_flagged() {
+    let root = scratch("signed-commits");
+    let upstream = repo(&root.join("upstream"));
+    let work = clone(&upstream, &root.join("work"));
+    let crawl = || Crawler::new(&work).require_signed_commits(true);
+    signed_commit(&work);
+    assert!(!states(crawl()).contains(&PendingState::UnsignedCommits));
+    commit(&work, "unsigned");
+    assert!(states(crawl()).contains(&PendingState::UnsignedCommits));
+}
+
+#[test]
+fn renamed_default_branch_is_flagged() {
+    let root = scratch("default-renamed");
+    let upstream = repo(&root.join("upstream"));
+    let work = clone(&upstream, &root.join("work"));
+    let crawl = || {
+        Crawler::new(&work)
+            .access_remote(Some("ssh-agent".into()))
+            .flag_default_renamed(true)
+    };
+    assert!(!states(crawl()).contains(&PendingState::DefaultBranchRenamed));
+    git(&upstream, &["branch", "-m", "main", "trunk"]);
+    assert!(states(crawl()).contains(&PendingState::DefaultBranchRenamed));
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_limit_stops_inspecting_early() {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    let root = scratch("parallel-limit");
+    for name in ["a", "b", "c", "d", "e", "f"] {
+        repo(&root.join(name));
+    }
+    let inspected = Arc::new(AtomicUsize::new(0));
+    let counter = Arc::clone(&inspected);
+    let outputs = Crawler::new(&root)
+        .parallel(2)
+        .limit(1)
+        .on_progress(Box::new(move |_| {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }))
+        .collect_parallel();
+    assert_eq!(outputs.len(), 1, "{outputs:?}");
+    // at most one more, already started on the other thread
+    assert!(inspected.load(Ordering::Relaxed) <= 2);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_profile_records_status_time() {
+    let root = scratch("parallel-profile");
+    for name in ["a", "b", "c"] {
+        repo(&root.join(name));
+    }
+    let mut crawler = Crawler::new(&root).profile(true).parallel(2);
+    assert_eq!(crawler.collect_parallel().len(), 3);
+    let profile = crawler.profile_report().unwrap();
+    assert!(profile.status > std::time::Duration::ZERO, "{profile:?}");
+}
+
+#[test]
+fn pending_found_before_a_remote_error_counts() {
+    let dir = repo(&scratch("pending-with-error").join("a"));
+    // without an origin, checking the remote fails
+    let crawl = || Crawler::new(&dir).access_remote(Some("ssh-agent".into()));
+    let outputs: Vec<_> = crawl().collect();
+    assert!(outputs[0].error.is_some(), "{outputs:?}");
+    assert!(!crawl().has_pending());
+    write(&dir.join("new"), "");
+    assert!(crawl().has_pending());
+}